@@ -0,0 +1,115 @@
+// Single scattering through the atmosphere, shared by the sky and the aerial perspective on
+// the terrain
+#define PLANET_RADIUS 6371e3
+#define ATMOSPHERE_RADIUS 6471e3
+#define PI 3.14159265
+
+uniform vec3 sun_position; // unit vector towards the sun
+uniform vec3 rayleigh_coefficients;
+uniform float mie_coefficient;
+uniform float rayleigh_scale_height;
+uniform float mie_scale_height;
+uniform float mie_anisotropy;
+uniform float sun_intensity;
+uniform float metres_per_unit;
+uniform float exposure;
+uniform vec3 night_sky_color;
+
+// Distances along the ray to where it enters and leaves a sphere around the planet center,
+// entry after exit when it misses
+vec2 ray_sphere_intersection(vec3 origin, vec3 direction, float radius)
+{
+    float b = dot(origin, direction);
+    float c = dot(origin, origin) - radius * radius;
+    float discriminant = b * b - c;
+    if (discriminant < 0.0) {
+        return vec2(1e5, -1e5);
+    }
+    discriminant = sqrt(discriminant);
+    return vec2(-b - discriminant, -b + discriminant);
+}
+
+// Sunlight scattered towards the origin by the air along the first max_distance metres of the
+// ray, and the share of light from that distance that makes it through
+vec3 scatter(vec3 origin, vec3 direction, float max_distance, int primary_steps, int light_steps, out vec3 transmittance)
+{
+    transmittance = vec3(1);
+
+    vec2 atmosphere_hit = ray_sphere_intersection(origin, direction, ATMOSPHERE_RADIUS);
+    float start = max(atmosphere_hit.x, 0.0);
+    float end = min(atmosphere_hit.y, max_distance);
+    vec2 ground_hit = ray_sphere_intersection(origin, direction, PLANET_RADIUS);
+    if (ground_hit.x > 0.0) {
+        end = min(end, ground_hit.x);
+    }
+    if (end <= start) {
+        return vec3(0);
+    }
+
+    float step_size = (end - start) / float(primary_steps);
+
+    float mu = dot(direction, sun_position);
+    float g = mie_anisotropy;
+    float phase_rayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    float phase_mie = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + mu * mu))
+        / (pow(1.0 + g * g - 2.0 * mu * g, 1.5) * (2.0 + g * g));
+
+    vec3 total_rayleigh = vec3(0);
+    vec3 total_mie = vec3(0);
+    float optical_depth_rayleigh = 0.0;
+    float optical_depth_mie = 0.0;
+
+    for (int i = 0; i < primary_steps; i++) {
+        vec3 sample_position = origin + direction * (start + (float(i) + 0.5) * step_size);
+        float height = length(sample_position) - PLANET_RADIUS;
+
+        float density_rayleigh = exp(-height / rayleigh_scale_height) * step_size;
+        float density_mie = exp(-height / mie_scale_height) * step_size;
+        optical_depth_rayleigh += density_rayleigh;
+        optical_depth_mie += density_mie;
+
+        // The planet shadows samples the sun has set for
+        if (ray_sphere_intersection(sample_position, sun_position, PLANET_RADIUS).x > 0.0) {
+            continue;
+        }
+
+        float light_step_size = ray_sphere_intersection(sample_position, sun_position, ATMOSPHERE_RADIUS).y / float(light_steps);
+        float light_depth_rayleigh = 0.0;
+        float light_depth_mie = 0.0;
+        for (int j = 0; j < light_steps; j++) {
+            vec3 light_sample = sample_position + sun_position * ((float(j) + 0.5) * light_step_size);
+            float light_height = length(light_sample) - PLANET_RADIUS;
+            light_depth_rayleigh += exp(-light_height / rayleigh_scale_height) * light_step_size;
+            light_depth_mie += exp(-light_height / mie_scale_height) * light_step_size;
+        }
+
+        vec3 attenuation = exp(-(rayleigh_coefficients * (optical_depth_rayleigh + light_depth_rayleigh)
+            + mie_coefficient * 1.1 * (optical_depth_mie + light_depth_mie)));
+        total_rayleigh += density_rayleigh * attenuation;
+        total_mie += density_mie * attenuation;
+    }
+
+    transmittance = exp(-(rayleigh_coefficients * optical_depth_rayleigh + mie_coefficient * 1.1 * optical_depth_mie));
+    return sun_intensity * (phase_rayleigh * rayleigh_coefficients * total_rayleigh + phase_mie * mie_coefficient * total_mie);
+}
+
+// Maps scattered radiance into displayable colour
+vec3 expose(vec3 radiance)
+{
+    return 1.0 - exp(-exposure * radiance);
+}
+
+// Position of a world space point relative to the planet center, in metres
+vec3 atmosphere_position(vec3 world_position)
+{
+    return vec3(0, PLANET_RADIUS + max(world_position.y * metres_per_unit, 1.0), 0);
+}
+
+// Colour of the sky seen along a direction, with the night sky showing once the sun is gone
+vec3 sky_color(vec3 origin, vec3 direction, int primary_steps, int light_steps)
+{
+    vec3 transmittance;
+    vec3 radiance = scatter(origin, direction, 1e12, primary_steps, light_steps, transmittance);
+    float night = 1.0 - smoothstep(-0.2, 0.05, sun_position.y);
+    return expose(radiance) + night_sky_color * night;
+}
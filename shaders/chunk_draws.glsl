@@ -0,0 +1,12 @@
+// The per draw data of MeshPool. Each draw's base instance is its index, read through an
+// instanced attribute since gl_DrawID needs GLSL 4.60.
+struct ChunkDraw {
+    mat4 model_matrix;
+    int lod_index;
+};
+
+layout(std430, binding=3) readonly buffer ChunkDraws {
+    ChunkDraw chunk_draws[];
+};
+
+layout(location=5) in uint draw_index;
@@ -0,0 +1,11 @@
+#version 450 core
+
+out vec4 FragColor;
+
+layout(location=1) in vec3 color;
+
+
+void main()
+{
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 450 core
+
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 color;
+layout(location=1) out vec3 color_out;
+
+
+uniform mat4 transform_matrix;
+
+
+void main()
+{
+    gl_Position = vec4(position, 1) * transform_matrix;
+    color_out = color;
+}
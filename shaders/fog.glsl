@@ -0,0 +1,63 @@
+// Fog and aerial perspective between the camera and a surface, shared by the terrain and the
+// water. The including shader declares camera_position, the renderer defines the FOG_* values.
+#include "atmosphere.glsl"
+
+uniform int fog_mode;
+uniform float fog_start;
+uniform float fog_end;
+uniform float fog_density; // extinction per world unit
+uniform float fog_height_falloff;
+uniform float fog_base_height;
+
+// Aerial perspective, also fades surfaces into the sky close to the far plane
+uniform int aerial_perspective;
+uniform float view_distance;
+
+// Share of the fragment's colour the fog replaces, seen from the camera
+float fog_amount(float distance, vec3 direction)
+{
+    if (fog_mode == FOG_LINEAR) {
+        return clamp((distance - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+    }
+    if (fog_mode == FOG_EXPONENTIAL) {
+        return 1.0 - exp(-fog_density * distance);
+    }
+    if (fog_mode == FOG_HEIGHT) {
+        // Density falling off exponentially with height, integrated along the view ray
+        float camera_density = fog_density * exp(-fog_height_falloff * (camera_position.y - fog_base_height));
+        float rise = direction.y * distance * fog_height_falloff;
+        float optical_depth = camera_density * distance;
+        if (abs(rise) > 1e-4) {
+            optical_depth *= (1.0 - exp(-rise)) / rise;
+        }
+        return 1.0 - exp(-optical_depth);
+    }
+    return 0.0;
+}
+
+// The colour of a surface at position as the camera sees it through the air and the fog
+vec3 apply_fog(vec3 color, vec3 position)
+{
+    vec3 to_fragment = position - camera_position;
+    float distance = length(to_fragment);
+    vec3 direction = to_fragment / max(distance, 1e-4);
+    vec3 origin = atmosphere_position(camera_position);
+
+    // Aerial perspective, the air between the camera and the surface dims it and adds the
+    // light it scatters. Close to the far plane the surface fades into the sky.
+    float haze = fog_amount(distance, direction);
+    if (aerial_perspective != 0) {
+        vec3 transmittance;
+        vec3 inscattered = scatter(origin, direction, distance * metres_per_unit, 8, 4, transmittance);
+        color = color * transmittance + expose(inscattered);
+
+        haze = max(haze, smoothstep(0.75 * view_distance, view_distance, distance));
+    }
+
+    // Fog takes the colour of the sky at the horizon behind the fragment
+    if (haze > 0.0) {
+        vec3 horizon_direction = normalize(vec3(direction.x, max(direction.y, 0.01), direction.z));
+        color = mix(color, sky_color(origin, horizon_direction, 8, 4), haze);
+    }
+    return color;
+}
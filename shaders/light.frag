@@ -2,13 +2,15 @@
 
 out vec4 FragColor;
 
-layout(location=1) in vec3 ambient_material;
+layout(location=1) in vec3 normalVector;
+
+uniform vec3 light_color;
 
 
 void main()
 {
-    vec3 color = ambient_material;
+    // Shade the sides a little so the gizmo reads as a solid
+    float shade = 0.75 + 0.25 * normalize(normalVector).y;
+    vec3 color = light_color * shade;
     FragColor = vec4(color, 1.0);
 }
-
-
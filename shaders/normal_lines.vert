@@ -0,0 +1,16 @@
+#version 450 core
+
+// The terrain's own vertices are drawn as points
+#include "vertex_attributes.glsl"
+#include "chunk_draws.glsl"
+
+layout(location=0) out vec3 world_position_out;
+layout(location=1) out vec3 world_normal_out;
+
+
+void main()
+{
+    mat4 model_matrix = chunk_draws[draw_index].model_matrix;
+    world_position_out = vec3(vec4(position, 1) * model_matrix);
+    world_normal_out = normalize(normalVector * mat3(model_matrix));
+}
@@ -0,0 +1,14 @@
+#version 450 core
+
+layout(location=0) in vec3 position;
+
+#include "chunk_draws.glsl"
+
+
+uniform mat4 light_matrix;
+
+
+void main()
+{
+    gl_Position = vec4(position, 1) * chunk_draws[draw_index].model_matrix * light_matrix;
+}
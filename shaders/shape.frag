@@ -1,52 +1,173 @@
 #version 450 core
 
+// The renderer defines MAX_MATERIALS, MAX_CASCADES, MAX_LIGHTS and the LIGHT_*, FOG_* and DEBUG_VIEW_*
+// values. DEBUG_VIEW picks the permutation, wireframe is shaded normally.
+
 struct Light {
     vec3 position;
+    int kind;
+    vec3 direction; // the way the light travels
+    float inner_cutoff; // cosines of the spot cone angles
     vec3 ambient;
+    float outer_cutoff;
     vec3 diffuse;
+    float constant_attenuation;
     vec3 specular;
+    float linear_attenuation;
+    float quadratic_attenuation;
+};
+
+struct Material {
+    vec4 ambient;
+    vec4 diffuse;
+    vec4 specular; // shininess in w
 };
 
 out vec4 FragColor;
 
 layout(location=0) in vec3 frag_pos;
-layout(location=1) in vec3 ambient_material;
-layout(location=2) in vec3 diffuse_material;
-layout(location=3) in vec3 specular_material;
-layout(location=4) in float shininess_material;
-layout(location=5) in vec3 normalVector;
+layout(location=1) in vec3 normalVector;
+layout(location=2) flat in uint material_index;
+layout(location=3) in float height;
+layout(location=4) in float occlusion; // share of the sky visible
+layout(location=5) flat in int lod_index;
+
+
+uniform vec3 camera_position;
+
+// Shadows of the first light, no cascades turns them off
+uniform int cascade_count;
+uniform mat4 light_matrices[MAX_CASCADES];
+uniform vec4 cascade_splits; // view depth where each cascade ends
+uniform float shadow_bias;
+uniform vec3 camera_forward;
+uniform int pcf_radius;
+
+layout(std140, binding=0) uniform MaterialPalette {
+    Material materials[MAX_MATERIALS];
+    vec4 palette_settings; // x: use the height gradient
+};
 
+layout(binding=1) uniform sampler1D height_gradient;
 
-uniform layout(location=11) mat4 model_matrix;
-uniform layout(location=12) vec3 camera_position;
-uniform layout(location=13) Light light;
+layout(std430, binding=2) readonly buffer Lights {
+    int light_count;
+    Light lights[];
+};
 
-void main()
-{
-    mat3 scale_rotate_matrix = mat3(model_matrix);
+layout(binding=4) uniform sampler2DArrayShadow shadow_map;
 
-    vec3 actual_normal = normalize(normalVector * scale_rotate_matrix);
-    vec3 light_direction = normalize(light.position - frag_pos);
+const vec3 LOD_TINTS[4] = vec3[](
+    vec3(0.2, 0.9, 0.3),
+    vec3(0.2, 0.5, 1.0),
+    vec3(1.0, 0.8, 0.2),
+    vec3(1.0, 0.3, 0.8)
+);
 
-    //Ambient component
-    vec3 ambient = ambient_material * light.ambient;
+#include "fog.glsl"
 
-    //Diffuse component
-    vec3 diffuse = (max(0, dot(actual_normal, light_direction)) * diffuse_material) * light.diffuse;
+// How much of the first light reaches this fragment, averaged over a square of texels
+float shadow_visibility(vec3 normal)
+{
+    if (cascade_count == 0) {
+        return 1.0;
+    }
+
+    float view_depth = dot(frag_pos - camera_position, camera_forward);
+    int cascade = cascade_count - 1;
+    for (int i = 0; i < cascade_count; i++) {
+        if (view_depth < cascade_splits[i]) {
+            cascade = i;
+            break;
+        }
+    }
+
+    vec4 light_space = vec4(frag_pos + normal * shadow_bias, 1) * light_matrices[cascade];
+    vec3 coords = light_space.xyz / light_space.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0).xy);
+    float visibility = 0.0;
+    for (int x = -pcf_radius; x <= pcf_radius; x++) {
+        for (int y = -pcf_radius; y <= pcf_radius; y++) {
+            visibility += texture(shadow_map, vec4(coords.xy + vec2(x, y) * texel, cascade, coords.z));
+        }
+    }
+    float side = 2 * pcf_radius + 1;
+    return visibility / (side * side);
+}
 
-    //Specular component
-    vec3 camera_direction = normalize(camera_position - frag_pos);
-    vec3 reflection_direction = reflect(-light_direction, actual_normal);
-    float spec = pow(max(dot(camera_direction, reflection_direction), 0.0), shininess_material);
-    vec3 specular = (specular_material * spec) * light.specular;
+void main()
+{
+    Material material = materials[min(material_index, uint(MAX_MATERIALS - 1))];
 
+    vec3 ambient_material = material.ambient.rgb;
+    vec3 diffuse_material = material.diffuse.rgb;
+    vec3 specular_material = material.specular.rgb;
+    float shininess_material = material.specular.w;
 
-    vec3 color =  (ambient + diffuse + specular);
-    FragColor = vec4(color, 1.0);
-}
+    if (palette_settings.x > 0.5) {
+        vec3 gradient_color = texture(height_gradient, height).rgb;
+        ambient_material = gradient_color;
+        diffuse_material = gradient_color;
+    }
 
+    vec3 actual_normal = normalize(normalVector);
+    vec3 camera_direction = normalize(camera_position - frag_pos);
+
+#if DEBUG_VIEW == DEBUG_VIEW_NORMALS
+    FragColor = vec4(actual_normal * 0.5 + 0.5, 1.0);
+    return;
+#endif
+
+    vec3 ambient = vec3(0);
+    vec3 diffuse = vec3(0);
+    vec3 specular = vec3(0);
+
+    for (int i = 0; i < min(light_count, MAX_LIGHTS); i++) {
+        Light light = lights[i];
+
+        vec3 light_direction = -normalize(light.direction);
+        float intensity = 1.0;
+        if (light.kind != LIGHT_DIRECTIONAL) {
+            vec3 to_light = light.position - frag_pos;
+            float distance = length(to_light);
+            light_direction = to_light / distance;
+            intensity = 1.0 / (light.constant_attenuation
+                + light.linear_attenuation * distance
+                + light.quadratic_attenuation * distance * distance);
+        }
+        if (light.kind == LIGHT_SPOT) {
+            float spot_angle = dot(-light_direction, normalize(light.direction));
+            intensity *= smoothstep(light.outer_cutoff, light.inner_cutoff, spot_angle);
+        }
+
+        //Ambient component
+        ambient += ambient_material * light.ambient * intensity * occlusion;
+
+        if (i == 0) {
+            intensity *= shadow_visibility(actual_normal);
+        }
+
+        //Diffuse component
+        diffuse += (max(0, dot(actual_normal, light_direction)) * diffuse_material) * light.diffuse * intensity;
+
+        //Specular component
+        vec3 reflection_direction = reflect(-light_direction, actual_normal);
+        float spec = pow(max(dot(camera_direction, reflection_direction), 0.0), shininess_material);
+        specular += (specular_material * spec) * light.specular * intensity;
+    }
 
 
+    vec3 color =  (ambient + diffuse + specular);
 
+    color = apply_fog(color, frag_pos);
 
+#if DEBUG_VIEW == DEBUG_VIEW_LEVEL_OF_DETAIL
+    color = mix(color, LOD_TINTS[lod_index % 4], 0.5);
+#endif
 
+    FragColor = vec4(color, 1.0);
+}
@@ -10,41 +10,35 @@ struct Noise {
     float min_value;
 };
 
-layout(location=0) in vec3 position;
-layout(location=0) out vec3 frag_pos_out;
-
-layout(location=1) in vec3 ambient_material;
-layout(location=1) out vec3 ambient_material_out;
-
-layout(location=2) in vec3 diffuse_material;
-layout(location=2) out vec3 diffuse_material_out;
-
-layout(location=3) in vec3 specular_material;
-layout(location=3) out vec3 specular_material_out;
+#include "vertex_attributes.glsl"
+#include "chunk_draws.glsl"
 
-layout(location=4) in float shininess;
-layout(location=4) out float shininess_out;
-
-layout(location=5) in vec3 normalVector;
-layout(location=5) out vec3 normal_vector_out;
+layout(location=0) out vec3 frag_pos_out;
+layout(location=1) out vec3 normal_vector_out;
+layout(location=2) flat out uint material_index_out;
+layout(location=3) out float height_out;
+layout(location=4) out float occlusion_out;
+layout(location=5) flat out int lod_index_out;
 
 
-uniform layout(location=10) mat4 transform_matrix;
-uniform layout(location=11) mat4 model_matrix;
+uniform mat4 view_projection_matrix;
+// Geometry on the negative side of the plane is clipped when GL_CLIP_DISTANCE0 is enabled
+uniform vec4 clip_plane;
 
 
 void main()
 {
    
 
-    const vec4 transformed_pos = vec4(position, 1) * transform_matrix;
-    gl_Position = transformed_pos;
-    
-    frag_pos_out = vec3(vec4(position, 1) * model_matrix);
-
-    normal_vector_out = normalize(normalVector);
-    ambient_material_out = ambient_material;
-    diffuse_material_out = diffuse_material;
-    specular_material_out = specular_material;
-    shininess_out = shininess;
-}
\ No newline at end of file
+    ChunkDraw draw = chunk_draws[draw_index];
+
+    frag_pos_out = vec3(vec4(position, 1) * draw.model_matrix);
+    gl_Position = vec4(frag_pos_out, 1) * view_projection_matrix;
+    gl_ClipDistance[0] = dot(vec4(frag_pos_out, 1), clip_plane);
+
+    normal_vector_out = normalize(normalVector * mat3(draw.model_matrix));
+    material_index_out = material_index;
+    height_out = height;
+    occlusion_out = occlusion;
+    lod_index_out = draw.lod_index;
+}
@@ -0,0 +1,31 @@
+#version 450 core
+
+out vec4 FragColor;
+
+layout(location=0) in vec2 ndc;
+
+
+uniform mat4 inverse_view_projection;
+uniform vec3 camera_position;
+
+#include "atmosphere.glsl"
+
+#define SUN_ANGULAR_RADIUS 0.0047
+
+
+void main()
+{
+    vec4 far_point = vec4(ndc, 1, 1) * inverse_view_projection;
+    vec3 direction = normalize(far_point.xyz / far_point.w - camera_position);
+    vec3 origin = atmosphere_position(camera_position);
+
+    vec3 color = sky_color(origin, direction, 16, 8);
+
+    // The sun disk, dimmed by the air in front of it
+    vec3 transmittance;
+    scatter(origin, direction, 1e12, 4, 1, transmittance);
+    float disk = smoothstep(cos(SUN_ANGULAR_RADIUS * 1.2), cos(SUN_ANGULAR_RADIUS), dot(direction, sun_position));
+    color += disk * expose(transmittance * sun_intensity);
+
+    FragColor = vec4(color, 1.0);
+}
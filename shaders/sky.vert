@@ -0,0 +1,12 @@
+#version 450 core
+
+layout(location=0) out vec2 ndc_out;
+
+
+void main()
+{
+    // A triangle covering the whole screen, (-1, -1), (3, -1) and (-1, 3)
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(ndc, 1, 1);
+    ndc_out = ndc;
+}
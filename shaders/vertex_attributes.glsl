@@ -0,0 +1,7 @@
+// The attributes of Mesh::terrain_vertex_layout. Meshes with fewer attributes, like the light
+// gizmo, fill the first locations and leave the rest at their defaults.
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 normalVector;
+layout(location=2) in uint material_index;
+layout(location=3) in float height;
+layout(location=4) in float occlusion;
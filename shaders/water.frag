@@ -0,0 +1,81 @@
+#version 450 core
+
+struct DirectionalLight {
+    vec3 direction;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+out vec4 FragColor;
+
+layout(location=0) in vec3 frag_pos;
+// Water depth above the terrain
+layout(location=1) in float depth;
+
+
+uniform vec3 camera_position;
+uniform DirectionalLight sun;
+
+uniform float time;
+uniform vec3 shallow_color;
+uniform vec3 deep_color;
+uniform float max_depth;
+uniform float wave_scale;
+uniform float wave_strength;
+uniform vec2 screen_size;
+uniform int reflections;
+
+layout(binding=2) uniform sampler2D reflection_texture;
+layout(binding=3) uniform sampler2D normal_map;
+
+#include "fog.glsl"
+
+const float WATER_F0 = 0.02;
+const float WATER_SHININESS = 128.0;
+const float REFLECTION_DISTORTION = 0.03;
+
+void main()
+{
+    // Two layers of the wave normal map scrolling in different directions
+    vec2 uv = frag_pos.xz / wave_scale;
+    vec3 first_wave = texture(normal_map, uv + vec2(0.05, 0.03) * time).rgb * 2.0 - 1.0;
+    vec3 second_wave = texture(normal_map, uv * 1.7 + vec2(-0.04, 0.02) * time).rgb * 2.0 - 1.0;
+    vec3 wave_normal = normalize(first_wave + second_wave);
+
+    // The normal map's z points up, out of the water surface
+    vec3 normal = normalize(vec3(wave_normal.x * wave_strength, wave_normal.z, wave_normal.y * wave_strength));
+
+    vec3 camera_direction = normalize(camera_position - frag_pos);
+    vec3 light_direction = -normalize(sun.direction);
+
+    // Schlick's approximation, water reflects little when seen from straight above
+    float cos_theta = max(dot(normal, camera_direction), 0.0);
+    float fresnel = WATER_F0 + (1.0 - WATER_F0) * pow(1.0 - cos_theta, 5.0);
+
+    // Without the reflection pass the water mirrors the sky alone
+    vec3 reflection;
+    if (reflections != 0) {
+        vec2 screen_uv = gl_FragCoord.xy / screen_size + normal.xz * REFLECTION_DISTORTION;
+        reflection = texture(reflection_texture, clamp(screen_uv, 0.001, 0.999)).rgb;
+    } else {
+        vec3 reflected = reflect(-camera_direction, normal);
+        reflected.y = max(reflected.y, 0.01);
+        reflection = sky_color(atmosphere_position(camera_position), normalize(reflected), 8, 4);
+    }
+
+    float depth_factor = clamp(depth / max_depth, 0.0, 1.0);
+    vec3 water_color = mix(shallow_color, deep_color, depth_factor);
+    vec3 lit_water = water_color * (sun.ambient + max(dot(normal, light_direction), 0.0) * sun.diffuse);
+
+    vec3 halfway = normalize(light_direction + camera_direction);
+    vec3 specular = pow(max(dot(normal, halfway), 0.0), WATER_SHININESS) * sun.specular;
+
+    vec3 color = mix(lit_water, reflection, fresnel) + specular;
+
+    // Clear in the shallows, opaque in deep water and at grazing angles, fading out at the shore
+    float opacity = clamp(mix(0.35, 1.0, depth_factor) + fresnel, 0.0, 1.0);
+    opacity *= smoothstep(0.0, 0.15, depth);
+
+    FragColor = vec4(apply_fog(color, frag_pos), opacity);
+}
@@ -0,0 +1,20 @@
+#version 450 core
+
+layout(location=0) in vec3 position;
+layout(location=0) out vec3 frag_pos_out;
+
+layout(location=1) in float depth;
+layout(location=1) out float depth_out;
+
+
+uniform mat4 transform_matrix;
+uniform mat4 model_matrix;
+
+
+void main()
+{
+    gl_Position = vec4(position, 1) * transform_matrix;
+
+    frag_pos_out = vec3(vec4(position, 1) * model_matrix);
+    depth_out = depth;
+}
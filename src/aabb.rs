@@ -0,0 +1,38 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: glm::Vec3, max: glm::Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    // An inverted box that any point will grow
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: glm::vec3(f32::MAX, f32::MAX, f32::MAX),
+            max: glm::vec3(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn grow(&mut self, point: &glm::Vec3) {
+        self.min = glm::min2(&self.min, point);
+        self.max = glm::max2(&self.max, point);
+    }
+
+    pub fn translated(&self, offset: &glm::Vec3) -> Aabb {
+        Aabb::new(self.min + offset, self.max + offset)
+    }
+
+    // Distance from a point to the closest point of the box, zero inside it
+    pub fn distance_to(&self, point: &glm::Vec3) -> f32 {
+        let closest = glm::clamp_vec(point, &self.min, &self.max);
+        glm::distance(point, &closest)
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+}
@@ -0,0 +1,52 @@
+use crate::aabb::Aabb;
+
+// The six clip planes of a view-projection matrix, with normals pointing inwards
+pub struct Frustum {
+    planes: [glm::Vec4; 6],
+}
+
+impl Frustum {
+    // Gribb-Hartmann plane extraction for OpenGL clip space
+    pub fn from_matrix(view_projection_matrix: &glm::Mat4) -> Frustum {
+        let row = |i: usize| -> glm::Vec4 { view_projection_matrix.row(i).transpose() };
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in planes.iter_mut() {
+            let length = glm::length(&plane.xyz());
+            if length > 0.0 {
+                *plane /= length;
+            }
+        }
+
+        Frustum { planes }
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for plane in &self.planes {
+            // The corner furthest along the plane normal
+            let positive_vertex = glm::vec3(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+
+            if glm::dot(&plane.xyz(), &positive_vertex) + plane.w < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -1,5 +1,9 @@
 use glutin::event::VirtualKeyCode;
 
+use self::frustum::Frustum;
+
+pub mod frustum;
+
 pub struct Camera {
     pub position: glm::Vec3,
     pub direction: glm::Vec3,
@@ -39,6 +43,10 @@ impl Camera {
         glm::look_at(&self.position, &(self.position + self.front), &self.up)
     }
 
+    pub fn get_frustum(&self, projection_matrix: &glm::Mat4) -> Frustum {
+        Frustum::from_matrix(&(projection_matrix * self.get_look_at_matrix()))
+    }
+
     pub fn handle_key_input(&mut self, key: VirtualKeyCode, delta_time: f32) {
         match key {
             VirtualKeyCode::D => {
@@ -0,0 +1,162 @@
+use crate::camera::Camera;
+
+const DEFAULT_SIZE: (i32, i32) = (1280, 720);
+
+pub const USAGE: &str = "\
+Usage: terrain-generator [--render <file.png> [options]]
+
+Without --render the interactive viewer opens. With it, the terrain is rendered to a PNG
+file without opening a window.
+
+  --render <file.png>       Output file, turntable frames get the frame number appended
+  --size <width>x<height>   Image size, 1280x720 by default
+  --preset <file.toml>      Render preset with the world and lighting settings
+  --camera <x>,<y>,<z>      Camera position
+  --yaw <degrees>           Camera heading, -90 looks down the negative z axis
+  --pitch <degrees>         Camera tilt, negative looks down
+  --turntable <frames>      Render frames evenly spaced on a circle around the orbit
+                            center, keeping the camera's height and distance
+  --orbit-center <x>,<z>    Point the turntable circles around, the origin by default";
+
+// What to render offscreen, parsed from the command line
+pub struct CaptureOptions {
+    pub output: String,
+    pub size: (i32, i32),
+    pub preset: Option<String>,
+    pub camera_position: glm::Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub turntable_frames: Option<u32>,
+    pub orbit_center: glm::Vec2,
+}
+
+impl CaptureOptions {
+    // None when the arguments don't ask for an offscreen render
+    pub fn parse(args: &[String]) -> Result<Option<CaptureOptions>, String> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+
+        let default_camera = Camera::new();
+        let mut output = None;
+        let mut options = CaptureOptions {
+            output: String::new(),
+            size: DEFAULT_SIZE,
+            preset: None,
+            camera_position: default_camera.position,
+            yaw: default_camera.yaw,
+            pitch: default_camera.pitch,
+            turntable_frames: None,
+            orbit_center: glm::vec2(0.0, 0.0),
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", flag));
+            match flag.as_str() {
+                "--render" => output = Some(value()?.clone()),
+                "--size" => {
+                    let size = value()?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .ok_or_else(|| format!("Expected <width>x<height>, got {}", size))?;
+                    options.size = (parse_number(width)?, parse_number(height)?);
+                    if options.size.0 <= 0 || options.size.1 <= 0 {
+                        return Err(format!("The image size {} is empty", size));
+                    }
+                }
+                "--preset" => options.preset = Some(value()?.clone()),
+                "--camera" => {
+                    let [x, y, z] = parse_list(value()?)?;
+                    options.camera_position = glm::vec3(x, y, z);
+                }
+                "--yaw" => options.yaw = parse_number(value()?)?,
+                "--pitch" => options.pitch = parse_number(value()?)?,
+                "--turntable" => {
+                    let frames: u32 = parse_number(value()?)?;
+                    if frames == 0 {
+                        return Err("The turntable needs at least one frame".to_string());
+                    }
+                    options.turntable_frames = Some(frames);
+                }
+                "--orbit-center" => {
+                    let [x, z] = parse_list(value()?)?;
+                    options.orbit_center = glm::vec2(x, z);
+                }
+                _ => return Err(format!("Unknown argument {}", flag)),
+            }
+        }
+
+        options.output = output.ok_or("Offscreen rendering needs an output file, use --render")?;
+        Ok(Some(options))
+    }
+
+    // The camera of every image to render, one unless this is a turntable
+    pub fn cameras(&self) -> Vec<Camera> {
+        let mut camera = Camera::new();
+        camera.position = self.camera_position;
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+
+        let frames = match self.turntable_frames {
+            Some(frames) => frames,
+            None => {
+                camera.update_camera_vectors();
+                return vec![camera];
+            }
+        };
+
+        let offset = glm::vec2(
+            self.camera_position.x - self.orbit_center.x,
+            self.camera_position.z - self.orbit_center.y,
+        );
+        let radius = glm::length(&offset);
+        let start_angle = offset.y.atan2(offset.x);
+
+        (0..frames)
+            .map(|frame| {
+                let angle = start_angle + frame as f32 / frames as f32 * std::f32::consts::TAU;
+                let mut orbiting = Camera::new();
+                orbiting.position = glm::vec3(
+                    self.orbit_center.x + radius * angle.cos(),
+                    self.camera_position.y,
+                    self.orbit_center.y + radius * angle.sin(),
+                );
+                // Face the orbit center, the yaw angle is measured like the orbit angle
+                orbiting.yaw = (angle + std::f32::consts::PI).to_degrees();
+                orbiting.pitch = self.pitch;
+                orbiting.update_camera_vectors();
+                orbiting
+            })
+            .collect()
+    }
+
+    // Where an image goes, turntable frames are numbered before the extension
+    pub fn frame_path(&self, frame: usize) -> String {
+        if self.turntable_frames.is_none() {
+            return self.output.clone();
+        }
+        match self.output.rsplit_once('.') {
+            Some((stem, extension)) if !extension.contains('/') => {
+                format!("{}_{:04}.{}", stem, frame, extension)
+            }
+            _ => format!("{}_{:04}", self.output, frame),
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn parse_list<const N: usize>(text: &str) -> Result<[f32; N], String> {
+    let values = text
+        .split(',')
+        .map(parse_number)
+        .collect::<Result<Vec<f32>, String>>()?;
+    values
+        .try_into()
+        .map_err(|_| format!("Expected {} comma separated numbers, got {}", N, text))
+}
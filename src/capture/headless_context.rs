@@ -0,0 +1,138 @@
+use std::{
+    ffi::{c_void, CString},
+    ptr,
+};
+
+use libloading::{Library, Symbol};
+
+const EGL_LIBRARY_NAMES: [&str; 2] = ["libEGL.so.1", "libEGL.so"];
+
+// Mesa's platform for contexts that never present to a window. Without a GPU it falls back
+// to the llvmpipe software rasterizer.
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x0001;
+const EGL_NONE: i32 = 0x3038;
+
+type EglDisplay = *mut c_void;
+type EglConfig = *mut c_void;
+type EglContext = *mut c_void;
+type EglSurface = *mut c_void;
+
+// EGL_KHR_no_config_context
+const EGL_NO_CONFIG: EglConfig = ptr::null_mut();
+
+type GetPlatformDisplay = unsafe extern "C" fn(u32, *mut c_void, *const isize) -> EglDisplay;
+type Initialize = unsafe extern "C" fn(EglDisplay, *mut i32, *mut i32) -> u32;
+type BindApi = unsafe extern "C" fn(u32) -> u32;
+type CreateContext =
+    unsafe extern "C" fn(EglDisplay, EglConfig, EglContext, *const i32) -> EglContext;
+type MakeCurrent = unsafe extern "C" fn(EglDisplay, EglSurface, EglSurface, EglContext) -> u32;
+type DestroyContext = unsafe extern "C" fn(EglDisplay, EglContext) -> u32;
+type Terminate = unsafe extern "C" fn(EglDisplay) -> u32;
+type GetProcAddress = unsafe extern "C" fn(*const i8) -> *const c_void;
+
+// An OpenGL 4.5 core context without a window or a display server. EGL is loaded at runtime,
+// so the windowed viewer still starts on machines without it.
+pub struct HeadlessContext {
+    library: Library,
+    display: EglDisplay,
+    context: EglContext,
+}
+
+impl HeadlessContext {
+    // Creates the context, makes it current and loads the OpenGL functions
+    pub fn create() -> Result<HeadlessContext, String> {
+        let library = EGL_LIBRARY_NAMES
+            .iter()
+            .find_map(|name| unsafe { Library::new(name).ok() })
+            .ok_or_else(|| "Headless rendering needs libEGL, which wasn't found".to_string())?;
+
+        let (display, context) = unsafe {
+            let get_platform_display: Symbol<GetPlatformDisplay> =
+                symbol(&library, "eglGetPlatformDisplay")?;
+            let initialize: Symbol<Initialize> = symbol(&library, "eglInitialize")?;
+            let bind_api: Symbol<BindApi> = symbol(&library, "eglBindAPI")?;
+            let create_context: Symbol<CreateContext> = symbol(&library, "eglCreateContext")?;
+            let make_current: Symbol<MakeCurrent> = symbol(&library, "eglMakeCurrent")?;
+            let get_proc_address: Symbol<GetProcAddress> = symbol(&library, "eglGetProcAddress")?;
+
+            let display =
+                get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null());
+            if display.is_null() || initialize(display, ptr::null_mut(), ptr::null_mut()) == 0 {
+                return Err("Failed to open a surfaceless EGL display".to_string());
+            }
+            if bind_api(EGL_OPENGL_API) == 0 {
+                return Err("EGL doesn't support desktop OpenGL".to_string());
+            }
+
+            let context_attributes = [
+                EGL_CONTEXT_MAJOR_VERSION,
+                4,
+                EGL_CONTEXT_MINOR_VERSION,
+                5,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            // Without a surface there is nothing for a config to describe, so none is chosen
+            let context = create_context(
+                display,
+                EGL_NO_CONFIG,
+                ptr::null_mut(),
+                context_attributes.as_ptr(),
+            );
+            if context.is_null() {
+                return Err("Failed to create an OpenGL 4.5 core context".to_string());
+            }
+            // Rendering only ever goes into framebuffer objects
+            if make_current(display, ptr::null_mut(), ptr::null_mut(), context) == 0 {
+                return Err("Failed to make the headless context current".to_string());
+            }
+
+            gl::load_with(|name| {
+                let name = CString::new(name).unwrap();
+                get_proc_address(name.as_ptr())
+            });
+            (display, context)
+        };
+
+        Ok(HeadlessContext {
+            library,
+            display,
+            context,
+        })
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            if let Ok(make_current) = symbol::<MakeCurrent>(&self.library, "eglMakeCurrent") {
+                make_current(
+                    self.display,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+            }
+            if let Ok(destroy_context) =
+                symbol::<DestroyContext>(&self.library, "eglDestroyContext")
+            {
+                destroy_context(self.display, self.context);
+            }
+            if let Ok(terminate) = symbol::<Terminate>(&self.library, "eglTerminate") {
+                terminate(self.display);
+            }
+        }
+    }
+}
+
+unsafe fn symbol<'a, T>(library: &'a Library, name: &str) -> Result<Symbol<'a, T>, String> {
+    library
+        .get(name.as_bytes())
+        .map_err(|e| format!("libEGL is missing {}: {}", name, e))
+}
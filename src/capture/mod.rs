@@ -0,0 +1,108 @@
+pub mod capture_options;
+pub mod headless_context;
+pub mod render_preset;
+
+use std::{
+    fs,
+    io::BufWriter,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    chunk::{region_cache::RegionCache, ChunkContainer},
+    curve_editor::curve::Curve,
+    mesh::mesh_settings::MeshSettings,
+    noise_map::noise_map_settings::NoiseMapSettings,
+    renderer::{Renderer, SceneSettings},
+    CHUNK_CACHE_DIRECTORY, CHUNK_PIXEL_SIZE, VIEW_DISTANCE, WATER_LEVEL,
+};
+
+use self::{
+    capture_options::CaptureOptions, headless_context::HeadlessContext, render_preset::RenderPreset,
+};
+
+const SCREENSHOT_DIRECTORY: &str = "./screenshots";
+
+// Seconds between turntable frames, only the water moves with time
+const TURNTABLE_FRAME_TIME: f32 = 1.0 / 30.0;
+
+// Renders the images asked for on the command line without opening a window
+pub fn run(options: &CaptureOptions) -> Result<(), String> {
+    // Declared first so the context outlives every GL object below
+    let _context = HeadlessContext::create()?;
+
+    let preset = match &options.preset {
+        Some(path) => RenderPreset::load(path)?,
+        None => RenderPreset::default(),
+    };
+
+    let mut noise_map_settings = NoiseMapSettings::new();
+    let mut mesh_settings = MeshSettings::new(" Mesh".to_string(), 10.0, Curve::quadratic(), 0);
+    let mut scene_settings = SceneSettings::new(10.0, WATER_LEVEL);
+    preset.apply(
+        &mut noise_map_settings,
+        &mut mesh_settings,
+        &mut scene_settings,
+    );
+
+    let coloring = preset.palette()?.build_coloring();
+    let mut renderer = Renderer::new(&coloring, &scene_settings)?;
+    let mut chunk_container = ChunkContainer::new(
+        CHUNK_PIXEL_SIZE + 1,
+        VIEW_DISTANCE,
+        &coloring,
+        &noise_map_settings,
+        &mesh_settings,
+        preset.edits()?,
+        RegionCache::new(CHUNK_CACHE_DIRECTORY),
+    );
+
+    for (frame, camera) in options.cameras().iter().enumerate() {
+        // Every chunk in view has to be finished, placeholders would show up in the image
+        chunk_container.generate_visible_chunks(camera.position);
+        while chunk_container.is_generating() {
+            thread::sleep(Duration::from_millis(10));
+            chunk_container.update_chunk_map(usize::MAX);
+        }
+        chunk_container.generate_visible_chunks(camera.position);
+
+        let pixels = renderer.render_to_image(
+            &mut chunk_container,
+            camera,
+            &scene_settings,
+            frame as f32 * TURNTABLE_FRAME_TIME,
+            options.size,
+        );
+        let path = options.frame_path(frame);
+        save_png(&path, options.size, &pixels)?;
+        println!("Saved {}", path);
+    }
+    Ok(())
+}
+
+// A new file name in the screenshot directory
+pub fn screenshot_path() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    format!("{}/screenshot_{}.png", SCREENSHOT_DIRECTORY, timestamp)
+}
+
+// Writes rows of RGB pixels, top row first
+pub fn save_png(path: &str, size: (i32, i32), pixels: &[u8]) -> Result<(), String> {
+    if let Some(directory) = Path::new(path).parent() {
+        fs::create_dir_all(directory)
+            .map_err(|e| format!("Could not create {}: {}", directory.display(), e))?;
+    }
+    let file = fs::File::create(path).map_err(|e| format!("Could not create {}: {}", path, e))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size.0 as u32, size.1 as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| format!("Could not write {}: {}", path, e))
+}
@@ -0,0 +1,80 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::{
+    material::palette::{Palette, PaletteFile},
+    mesh::mesh_settings::MeshSettings,
+    noise_map::noise_map_settings::NoiseMapSettings,
+    renderer::SceneSettings,
+    sculpt::terrain_edits::TerrainEdits,
+};
+
+// The world and lighting of an offscreen render, read from a TOML file. Anything left out
+// keeps the viewer's default, and the palette and edits files are only used when named, so
+// renders don't change with whatever was last saved in the viewer.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderPreset {
+    pub palette: Option<String>,
+    pub edits: Option<String>,
+
+    pub seed: Option<i32>,
+    pub scale: Option<f64>,
+    pub octaves: Option<i32>,
+    pub persistence: Option<f64>,
+    pub lacunarity: Option<f64>,
+    pub strength: Option<f32>,
+
+    pub hour: Option<f32>,
+    pub sea_level: Option<f32>,
+    pub shadows: Option<bool>,
+    pub reflections: Option<bool>,
+}
+
+impl RenderPreset {
+    pub fn load(path: &str) -> Result<RenderPreset, String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        toml::from_str(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn palette(&self) -> Result<Palette, String> {
+        match &self.palette {
+            Some(path) => PaletteFile::new(path).load(),
+            None => Ok(Palette::standard()),
+        }
+    }
+
+    pub fn edits(&self) -> Result<TerrainEdits, String> {
+        match &self.edits {
+            Some(path) => TerrainEdits::load(path),
+            None => Ok(TerrainEdits::default()),
+        }
+    }
+
+    pub fn apply(
+        &self,
+        noise_map_settings: &mut NoiseMapSettings,
+        mesh_settings: &mut MeshSettings,
+        scene_settings: &mut SceneSettings,
+    ) {
+        set(&mut noise_map_settings.seed, self.seed);
+        set(&mut noise_map_settings.scale, self.scale);
+        set(&mut noise_map_settings.octaves, self.octaves);
+        set(&mut noise_map_settings.persistence, self.persistence);
+        set(&mut noise_map_settings.lacunarity, self.lacunarity);
+        set(&mut mesh_settings.strength, self.strength);
+
+        set(&mut scene_settings.time_of_day.hour, self.hour);
+        set(&mut scene_settings.water.sea_level, self.sea_level);
+        set(&mut scene_settings.shadows.enabled, self.shadows);
+        set(&mut scene_settings.water.reflections, self.reflections);
+    }
+}
+
+fn set<T>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
@@ -1,43 +1,89 @@
-use std::thread::{self, JoinHandle};
+use std::{
+    cell::Cell,
+    thread::{self, JoinHandle},
+};
 
 use crate::{
+    aabb::Aabb,
+    chunk::{
+        neighbourhood::Neighbourhood,
+        region_cache::{CacheHandle, RegionCache},
+    },
+    height_field::HeightField,
     lod::LevelOfDetailInfo,
-    material::Material,
-    mesh::{mesh_settings::MeshSettings, Mesh},
+    material::TerrainColoring,
+    mesh::{
+        mesh_pool::{MeshPool, PooledMesh},
+        mesh_settings::MeshSettings,
+        Mesh,
+    },
     noise_map::noise_map_settings::NoiseMapSettings,
     scenenode::SceneNode,
     CHUNK_PIXEL_SIZE,
 };
 
-#[derive(Clone)]
 pub struct Chunk {
     pub position: (i32, i32),
 
     meshes: Vec<Mesh>,
-    pub lod_index: usize,
+    // The meshes in the mesh pool, empty until uploaded. Dropping the chunk frees them.
+    pooled_meshes: Vec<PooledMesh>,
+    // Level of detail picked when the chunk was last added to a scene
+    pub lod_index: Cell<usize>,
+
+    pub height_field: HeightField,
 }
 
 impl Chunk {
     pub fn create_chunk(
         position: (i32, i32),
-        materials: &Vec<Material>,
+        coloring: &TerrainColoring,
         noise_map_settings: &NoiseMapSettings,
         mesh_settings: &MeshSettings,
         level_of_details: &Vec<LevelOfDetailInfo>,
+        neighbourhood: &Neighbourhood,
+        cache: &CacheHandle,
     ) -> Self {
         let mut meshes = Vec::new();
 
-        let mut adjusted_noise_map_settings = noise_map_settings.clone();
-        adjusted_noise_map_settings.offset_x = position.0 as f64 * CHUNK_PIXEL_SIZE as f64;
-        adjusted_noise_map_settings.offset_y = position.1 as f64 * CHUNK_PIXEL_SIZE as f64;
+        let chunk_noise_map_settings = Chunk::noise_map_settings_at(position, noise_map_settings);
+
+        // Only reading and writing the region files holds the lock, the other threads
+        // generating chunks wait on it
+        let cached_data = cache
+            .cache
+            .lock()
+            .unwrap()
+            .load(position, cache.settings_hash);
+        let height_field = cached_data
+            .and_then(|data| RegionCache::decode(&data, mesh_settings))
+            .unwrap_or_else(|| {
+                let height_field = HeightField::generate(&chunk_noise_map_settings, mesh_settings);
+                let stored = RegionCache::encode(&height_field)
+                    .map_err(|e| format!("Could not encode chunk {:?}: {}", position, e))
+                    .and_then(|data| {
+                        let mut region_cache = cache.cache.lock().unwrap();
+                        region_cache.store(position, cache.settings_hash, &data)
+                    });
+                if let Err(error) = stored {
+                    println!("{}", error);
+                }
+                height_field
+            })
+            .with_edits(mesh_settings, neighbourhood.edits.layer(position))
+            .with_occlusion(neighbourhood.border_heights(
+                position,
+                noise_map_settings,
+                mesh_settings,
+            ));
 
         for lod in level_of_details {
             let mut adjusted_mesh_settings = mesh_settings.clone();
             adjusted_mesh_settings.level_of_detail = lod.lod as i32;
 
             meshes.push(Mesh::create_terrain_mesh(
-                materials,
-                &adjusted_noise_map_settings,
+                coloring,
+                &height_field,
                 &adjusted_mesh_settings,
             ))
         }
@@ -45,18 +91,66 @@ impl Chunk {
         Self {
             position,
             meshes,
-            lod_index: 0,
+            pooled_meshes: Vec::new(),
+            lod_index: Cell::new(0),
+            height_field,
         }
     }
 
+    // The noise map settings are shared by all chunks, offset to the chunk's position
+    pub fn noise_map_settings_at(
+        position: (i32, i32),
+        noise_map_settings: &NoiseMapSettings,
+    ) -> NoiseMapSettings {
+        let mut adjusted_noise_map_settings = *noise_map_settings;
+        adjusted_noise_map_settings.offset_x = position.0 as f64 * CHUNK_PIXEL_SIZE as f64;
+        adjusted_noise_map_settings.offset_y = position.1 as f64 * CHUNK_PIXEL_SIZE as f64;
+        adjusted_noise_map_settings
+    }
+
+    // The chunk holding the vertex at integer world coordinates (x, z), and the vertex's
+    // position on that chunk's grid
+    pub fn vertex_location(x: i32, z: i32) -> ((i32, i32), i32, i32) {
+        let chunk_coordinates = (
+            (x as f32 / CHUNK_PIXEL_SIZE as f32).round() as i32,
+            (z as f32 / CHUNK_PIXEL_SIZE as f32).round() as i32,
+        );
+        let half_chunk_size = CHUNK_PIXEL_SIZE / 2;
+        let grid_x = x - chunk_coordinates.0 * CHUNK_PIXEL_SIZE + half_chunk_size;
+        let grid_z = half_chunk_size - (z - chunk_coordinates.1 * CHUNK_PIXEL_SIZE);
+        (chunk_coordinates, grid_x, grid_z)
+    }
+
+    // Every chunk holding the vertex, since neighbouring chunks share their border vertices
+    pub fn shared_vertex_locations(x: i32, z: i32) -> Vec<((i32, i32), i32, i32)> {
+        let (chunk, grid_x, grid_z) = Chunk::vertex_location(x, z);
+
+        let neighbours = |grid: i32| match grid {
+            0 => vec![(0, grid), (-1, CHUNK_PIXEL_SIZE)],
+            _ if grid == CHUNK_PIXEL_SIZE => vec![(0, grid), (1, 0)],
+            _ => vec![(0, grid)],
+        };
+
+        let mut locations = Vec::new();
+        for (offset_x, shared_x) in neighbours(grid_x) {
+            // Grid z runs opposite to the chunk z coordinate
+            for (offset_z, shared_z) in neighbours(grid_z) {
+                locations.push(((chunk.0 + offset_x, chunk.1 - offset_z), shared_x, shared_z));
+            }
+        }
+        locations
+    }
+
     pub fn request_chunk_generation(
         position: (i32, i32),
-        materials: &Vec<Material>,
+        coloring: &TerrainColoring,
         noise_map_settings: &NoiseMapSettings,
         mesh_settings: &MeshSettings,
         level_of_details: &Vec<LevelOfDetailInfo>,
+        neighbourhood: Neighbourhood,
+        cache: CacheHandle,
     ) -> JoinHandle<Chunk> {
-        let material_clone = materials.clone();
+        let coloring_clone = coloring.clone();
         let noise_map_settings_clone = noise_map_settings.clone();
         let mesh_settings_clone = mesh_settings.clone();
         let level_of_details_clone = level_of_details.to_vec();
@@ -64,35 +158,71 @@ impl Chunk {
         thread::spawn(move || {
             let chunk = Chunk::create_chunk(
                 position,
-                &material_clone,
+                &coloring_clone,
                 &noise_map_settings_clone,
                 &mesh_settings_clone,
                 &level_of_details_clone,
+                &neighbourhood,
+                &cache,
             );
 
             chunk
         })
     }
 
-    pub fn rebind_vaos(&mut self) {
-        for mesh in &mut self.meshes {
-            unsafe { mesh.create_vao() };
+    pub fn upload_meshes(&mut self, mesh_pool: &mut MeshPool) {
+        while !self.is_uploaded() {
+            self.upload_next_mesh(mesh_pool);
         }
     }
 
-    pub fn get_scene_node(&self, shader_id: u32, lod: usize) -> SceneNode {
-        let mesh_to_use = &self.meshes[lod];
+    // Uploads the next level of detail that isn't in the pool yet
+    pub fn upload_next_mesh(&mut self, mesh_pool: &mut MeshPool) {
+        let mesh = &self.meshes[self.pooled_meshes.len()];
+        self.pooled_meshes.push(mesh_pool.upload(mesh));
+    }
+
+    // Bytes upload_next_mesh would write to the pool, see MeshPool::upload_cost
+    pub fn next_upload_cost(&self, mesh_pool: &mut MeshPool) -> usize {
+        self.meshes
+            .get(self.pooled_meshes.len())
+            .map_or(0, |mesh| mesh_pool.upload_cost(mesh))
+    }
+
+    // Whether every level of detail is in the pool
+    pub fn is_uploaded(&self) -> bool {
+        self.pooled_meshes.len() == self.meshes.len()
+    }
+
+    pub fn mesh(&self, lod: usize) -> &Mesh {
+        &self.meshes[lod]
+    }
+
+    // Material of the full resolution vertex at grid coordinates (x, z)
+    pub fn material_at(&self, x: usize, z: usize) -> usize {
+        let mesh = &self.meshes[0];
+        mesh.material_index(z * self.height_field.size + x) as usize
+    }
+
+    pub fn world_position(&self) -> glm::Vec3 {
+        glm::vec3(
+            self.position.0 as f32 * CHUNK_PIXEL_SIZE as f32,
+            0.0,
+            self.position.1 as f32 * CHUNK_PIXEL_SIZE as f32,
+        )
+    }
+
+    // World space bounds, taken from the full detail mesh which contains every other level
+    pub fn bounds(&self) -> Aabb {
+        self.meshes[0].bounds.translated(&self.world_position())
+    }
 
+    pub fn get_scene_node(&self, lod: usize) -> SceneNode {
         SceneNode {
-            vao_id: mesh_to_use.vao_id,
-            index_count: mesh_to_use.index_count,
-            shader_program: shader_id,
-
-            position: glm::vec3(
-                self.position.0 as f32 * CHUNK_PIXEL_SIZE as f32,
-                0.0,
-                self.position.1 as f32 * CHUNK_PIXEL_SIZE as f32,
-            ),
+            mesh: self.pooled_meshes.get(lod).map(PooledMesh::range),
+            lod_index: lod,
+
+            position: self.world_position(),
             rotation: glm::vec3(0.0, 0.0, 0.0),
             scale: glm::vec3(1.0, 1.0, 1.0),
             reference_point: glm::vec3(0.0, 0.0, 0.0),
@@ -1,37 +1,167 @@
-use std::{collections::HashMap, rc::Rc, thread::JoinHandle};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
 
 use crate::{
-    lod::LevelOfDetailInfo, material::Material, mesh::mesh_settings::MeshSettings,
-    noise_map::noise_map_settings::NoiseMapSettings, scenenode::SceneNode,
+    aabb::Aabb,
+    camera::frustum::Frustum,
+    height_field::{self, ambient_occlusion::BORDER_WIDTH, raycast, SurfaceQuery},
+    lod::LevelOfDetailInfo,
+    material::TerrainColoring,
+    mesh::{mesh_pool::MeshPool, mesh_settings::MeshSettings, Mesh},
+    noise_map::{noise_map_settings::NoiseMapSettings, NoiseSampler},
+    ray::{Ray, RayHit},
+    scenenode::SceneNode,
+    sculpt::{brush::Brush, terrain_edits::TerrainEdits},
+    CHUNK_PIXEL_SIZE,
 };
 
-use self::chunk::Chunk;
+use self::{
+    chunk::Chunk,
+    neighbourhood::Neighbourhood,
+    region_cache::{settings_hash, CacheHandle, RegionCache},
+};
 pub mod chunk;
+pub mod neighbourhood;
+pub mod region_cache;
+pub mod streaming_settings;
+
+// Chunks further than this past the view distance are dropped. The margin keeps chunks at the
+// edge from being generated again each time the camera moves back and forth.
+const EVICTION_MARGIN: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoadState {
+    // Still shown as the shared default chunk while it is generated
+    Placeholder,
+    Generated,
+    // Generated, with a new mesh for its edits on the way
+    Remeshing,
+}
+
+// Where the heights of one chunk are read from
+enum HeightSource {
+    Chunk(Rc<Chunk>),
+    Noise(Box<NoiseSampler>),
+}
+
+// Reads heights at integer world coordinates, from the loaded chunks where possible and
+// from the noise elsewhere
+struct HeightLookup<'a> {
+    container: &'a ChunkContainer,
+    sources: HashMap<(i32, i32), HeightSource>,
+}
+
+impl<'a> HeightLookup<'a> {
+    fn new(container: &'a ChunkContainer) -> Self {
+        HeightLookup {
+            container,
+            sources: HashMap::new(),
+        }
+    }
+
+    fn source(&mut self, chunk_coordinates: (i32, i32)) -> &HeightSource {
+        let container = self.container;
+        self.sources.entry(chunk_coordinates).or_insert_with(|| {
+            match container.loaded_chunk(chunk_coordinates) {
+                Some(chunk) => HeightSource::Chunk(Rc::clone(chunk)),
+                None => HeightSource::Noise(Box::new(NoiseSampler::new(
+                    Chunk::noise_map_settings_at(chunk_coordinates, &container.noise_map_settings),
+                ))),
+            }
+        })
+    }
+
+    fn procedural_height(
+        &mut self,
+        chunk_coordinates: (i32, i32),
+        grid_x: i32,
+        grid_z: i32,
+    ) -> f32 {
+        let mesh_settings = &self.container.mesh_settings;
+        match self.source(chunk_coordinates) {
+            HeightSource::Chunk(chunk) => chunk.height_field.procedural_height(grid_x, grid_z),
+            HeightSource::Noise(sampler) => {
+                mesh_settings
+                    .curve
+                    .evaluate(sampler.sample(grid_x as f64, grid_z as f64)) as f32
+                    * mesh_settings.strength
+            }
+        }
+    }
+
+    // The height as it is meshed, so loaded chunks may lag behind the latest edits
+    fn height(&mut self, x: i32, z: i32) -> f32 {
+        let (chunk_coordinates, grid_x, grid_z) = Chunk::vertex_location(x, z);
+        match self.source(chunk_coordinates) {
+            HeightSource::Chunk(chunk) => chunk.height_field.height(grid_x, grid_z),
+            HeightSource::Noise(_) => self.edited_height(x, z),
+        }
+    }
+
+    // The height including every edit made so far
+    fn edited_height(&mut self, x: i32, z: i32) -> f32 {
+        let (chunk_coordinates, grid_x, grid_z) = Chunk::vertex_location(x, z);
+        self.procedural_height(chunk_coordinates, grid_x, grid_z)
+            + self
+                .container
+                .edits
+                .delta(chunk_coordinates, grid_x, grid_z)
+    }
+}
 
 pub struct ChunkContainer {
     chunk_size: i32,
     chunks_visible_in_view_dst: i32,
     chunk_map: HashMap<(i32, i32), Rc<Chunk>>,
     current_visible_chunks: Vec<Rc<Chunk>>,
+    // The chunk the view is centered on
+    center_chunk_coordinates: (i32, i32),
 
     chunks_in_queue: Vec<JoinHandle<Chunk>>,
+    // Finished chunks whose meshes aren't all in the mesh pool yet
+    upload_queue: Vec<Chunk>,
+    // Where the camera was when the visible chunks were last generated, uploads start closest
+    // to it
+    camera_position: glm::Vec3,
+    // Loaded chunks being meshed again after an edit
+    edited_chunks_in_queue: HashSet<(i32, i32)>,
 
     default_chunk: Rc<Chunk>,
+    mesh_pool: MeshPool,
 
     noise_map_settings: NoiseMapSettings,
     mesh_settings: MeshSettings,
-    materials: Vec<Material>,
+    coloring: TerrainColoring,
 
     detail_levels: Vec<LevelOfDetailInfo>,
+
+    edits: TerrainEdits,
+    cache: Arc<Mutex<RegionCache>>,
+    // Hash of the noise settings chunks are requested with. Chunks still generating when it
+    // changes are cached under the hash they were requested with.
+    settings_hash: u64,
+
+    // Visible chunks drawn and skipped by frustum culling in the last generated scene
+    pub chunks_drawn: usize,
+    pub chunks_culled: usize,
+    // Mesh data uploaded to the GPU by the last update of the chunk map, including the data
+    // copied when the mesh pool grew
+    pub bytes_uploaded: usize,
 }
 
 impl ChunkContainer {
     pub fn new(
         chunk_size: i32,
         view_distance: f32,
-        materials: &Vec<Material>,
+        coloring: &TerrainColoring,
         noise_map_settings: &NoiseMapSettings,
         mesh_settings: &MeshSettings,
+        edits: TerrainEdits,
+        mut cache: RegionCache,
     ) -> Self {
         let chunks_visible_in_view_dst = (view_distance / chunk_size as f32).round() as i32;
 
@@ -42,44 +172,109 @@ impl ChunkContainer {
             LevelOfDetailInfo::new(4, 600.0),
         ];
 
+        let settings_hash = settings_hash(noise_map_settings);
+        cache.set_settings_hash(settings_hash);
+        let cache = Arc::new(Mutex::new(cache));
+
         Self {
             chunk_size,
             chunks_visible_in_view_dst,
             chunk_map: HashMap::new(),
             current_visible_chunks: Vec::new(),
+            center_chunk_coordinates: (0, 0),
             chunks_in_queue: Vec::new(),
+            upload_queue: Vec::new(),
+            camera_position: glm::vec3(0.0, 0.0, 0.0),
+            edited_chunks_in_queue: HashSet::new(),
             default_chunk: Rc::new(Chunk::create_chunk(
                 (0, 0),
-                &materials,
+                coloring,
                 &noise_map_settings,
                 &mesh_settings,
                 &detail_levels,
+                &Neighbourhood::new((0, 0), &edits),
+                &CacheHandle {
+                    cache: Arc::clone(&cache),
+                    settings_hash,
+                },
             )),
-            materials: materials.clone(),
+            mesh_pool: MeshPool::new(Mesh::terrain_vertex_layout(mesh_settings.compact_vertices)),
+            coloring: coloring.clone(),
             noise_map_settings: noise_map_settings.clone(),
             mesh_settings: mesh_settings.clone(),
             detail_levels,
+            edits,
+            cache,
+            settings_hash,
+            chunks_drawn: 0,
+            chunks_culled: 0,
+            bytes_uploaded: 0,
         }
     }
 
     pub fn update_settings(
         &mut self,
-        materials: &Vec<Material>,
+        coloring: &TerrainColoring,
         noise_map_settings: &NoiseMapSettings,
         mesh_settings: &MeshSettings,
     ) {
-        self.materials = materials.clone();
+        self.coloring = coloring.clone();
         self.noise_map_settings = noise_map_settings.clone();
         self.mesh_settings = mesh_settings.clone();
+
+        self.settings_hash = settings_hash(noise_map_settings);
+        self.cache
+            .lock()
+            .unwrap()
+            .set_settings_hash(self.settings_hash);
+    }
+
+    fn cache_handle(&self) -> CacheHandle {
+        CacheHandle {
+            cache: Arc::clone(&self.cache),
+            settings_hash: self.settings_hash,
+        }
+    }
+
+    // Edits and loaded neighbours for generating the chunk at position
+    fn neighbourhood(&self, position: (i32, i32)) -> Neighbourhood {
+        let mut neighbourhood = Neighbourhood::new(position, &self.edits);
+        for z_offset in -1..=1 {
+            for x_offset in -1..=1 {
+                let neighbour = (position.0 + x_offset, position.1 + z_offset);
+                match self.loaded_chunk(neighbour) {
+                    Some(chunk) if neighbour != position => {
+                        neighbourhood
+                            .add_loaded(neighbour, chunk.height_field.procedural_heights());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        neighbourhood
+    }
+
+    // Chunks loaded from and added to the cache since startup
+    pub fn cache_stats(&self) -> (usize, usize) {
+        let cache = self.cache.lock().unwrap();
+        (cache.hits, cache.misses)
+    }
+
+    // Drops cached chunks generated with other settings
+    pub fn prune_cache(&mut self) -> Result<usize, String> {
+        self.cache.lock().unwrap().prune()
     }
 
     pub fn generate_visible_chunks(&mut self, camera_position: glm::Vec3) {
         self.current_visible_chunks.clear();
+        self.camera_position = camera_position;
 
         let current_chunk_coordinates = (
             (camera_position.x / self.chunk_size as f32).round() as i32,
             (camera_position.z / self.chunk_size as f32).round() as i32,
         );
+        self.center_chunk_coordinates = current_chunk_coordinates;
+        self.evict_distant_chunks();
 
         for y_offset in -self.chunks_visible_in_view_dst..=self.chunks_visible_in_view_dst {
             for x_offset in -self.chunks_visible_in_view_dst..=self.chunks_visible_in_view_dst {
@@ -94,12 +289,15 @@ impl ChunkContainer {
                 } else {
                     let handle = Chunk::request_chunk_generation(
                         chunk_coordinates,
-                        &self.materials,
+                        &self.coloring,
                         &self.noise_map_settings,
                         &self.mesh_settings,
                         &self.detail_levels,
+                        self.neighbourhood(chunk_coordinates),
+                        self.cache_handle(),
                     );
                     self.chunks_in_queue.push(handle);
+                    self.edits.mark_clean(chunk_coordinates);
 
                     self.chunk_map
                         .insert(chunk_coordinates, Rc::clone(&self.default_chunk));
@@ -111,22 +309,194 @@ impl ChunkContainer {
         }
     }
 
-    pub fn update_chunk_map(&mut self) {
+    // Drops the chunks far outside the view, which frees their ranges of the mesh pool
+    fn evict_distant_chunks(&mut self) {
+        let center = self.center_chunk_coordinates;
+        let range = self.chunks_visible_in_view_dst + EVICTION_MARGIN;
+        let is_near = |position: (i32, i32)| {
+            (position.0 - center.0).abs() <= range && (position.1 - center.1).abs() <= range
+        };
+
+        self.chunk_map.retain(|position, _| is_near(*position));
+    }
+
+    // The chunk generated at the coordinates, None while the default chunk holds its place
+    fn loaded_chunk(&self, chunk_coordinates: (i32, i32)) -> Option<&Rc<Chunk>> {
+        self.chunk_map
+            .get(&chunk_coordinates)
+            .filter(|chunk| !Rc::ptr_eq(chunk, &self.default_chunk))
+    }
+
+    // The chunks in view that have finished generating
+    pub fn visible_chunks(&self) -> Vec<Rc<Chunk>> {
+        self.current_visible_chunks
+            .iter()
+            .filter(|chunk| !Rc::ptr_eq(chunk, &self.default_chunk))
+            .cloned()
+            .collect()
+    }
+
+    // World space bounds and load state of every chunk in view. Placeholders get the default
+    // chunk's bounds moved to where they will be.
+    pub fn load_states(&self) -> Vec<(Aabb, LoadState)> {
+        let range = -self.chunks_visible_in_view_dst..=self.chunks_visible_in_view_dst;
+        let mut states = Vec::new();
+
+        for y_offset in range.clone() {
+            for x_offset in range.clone() {
+                let chunk_coordinates = (
+                    self.center_chunk_coordinates.0 + x_offset,
+                    self.center_chunk_coordinates.1 + y_offset,
+                );
+                let chunk = match self.chunk_map.get(&chunk_coordinates) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+
+                let state = if Rc::ptr_eq(chunk, &self.default_chunk) {
+                    LoadState::Placeholder
+                } else if self.edited_chunks_in_queue.contains(&chunk_coordinates) {
+                    LoadState::Remeshing
+                } else {
+                    LoadState::Generated
+                };
+                let offset = glm::vec3(
+                    (chunk_coordinates.0 - chunk.position.0) as f32 * CHUNK_PIXEL_SIZE as f32,
+                    0.0,
+                    (chunk_coordinates.1 - chunk.position.1) as f32 * CHUNK_PIXEL_SIZE as f32,
+                );
+                states.push((chunk.bounds().translated(&offset), state));
+            }
+        }
+        states
+    }
+
+    // Collects the chunks that finished generating and uploads their meshes, nearest to the
+    // camera first, until upload_budget bytes have gone up this frame. A chunk replaces its
+    // placeholder or old mesh once all its levels of detail are uploaded.
+    pub fn update_chunk_map(&mut self, upload_budget: usize) {
         let mut unfinished_threads: Vec<JoinHandle<Chunk>> = Vec::new();
 
         for handle in self.chunks_in_queue.drain(..) {
             if handle.is_finished() {
-                let mut chunk = handle.join().unwrap();
-
-                chunk.rebind_vaos();
-
-                self.chunk_map.insert(chunk.position, Rc::new(chunk));
+                self.upload_queue.push(handle.join().unwrap());
             } else {
                 unfinished_threads.push(handle);
             }
         }
 
         self.chunks_in_queue = unfinished_threads;
+
+        // Chunks that left the view while they waited aren't worth uploading. Their entries go
+        // with them, so they are generated again with the edits of the time if they come back.
+        let center = self.center_chunk_coordinates;
+        let range = self.chunks_visible_in_view_dst;
+        let chunk_map = &mut self.chunk_map;
+        let edited_chunks_in_queue = &mut self.edited_chunks_in_queue;
+        self.upload_queue.retain(|chunk| {
+            let position = chunk.position;
+            let in_view =
+                (position.0 - center.0).abs() <= range && (position.1 - center.1).abs() <= range;
+            if !in_view {
+                chunk_map.remove(&position);
+                edited_chunks_in_queue.remove(&position);
+            }
+            in_view
+        });
+
+        // Farthest first, so the nearest chunk is at the end
+        let camera_position = self.camera_position;
+        let distance = |chunk: &Chunk| glm::distance(&camera_position, &chunk.world_position());
+        self.upload_queue
+            .sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+        self.bytes_uploaded = 0;
+        while let Some(chunk) = self.upload_queue.last_mut() {
+            // An upload that grows the pool copies all of it, which waits for the next frame
+            // unless nothing was uploaded yet
+            let cost = chunk.next_upload_cost(&mut self.mesh_pool);
+            if self.bytes_uploaded > 0 && self.bytes_uploaded + cost > upload_budget {
+                break;
+            }
+            chunk.upload_next_mesh(&mut self.mesh_pool);
+            self.bytes_uploaded += cost;
+
+            if chunk.is_uploaded() {
+                let chunk = self.upload_queue.pop().unwrap();
+                self.edited_chunks_in_queue.remove(&chunk.position);
+                self.chunk_map.insert(chunk.position, Rc::new(chunk));
+            }
+        }
+
+        // Mesh edited chunks again, keeping the old mesh on screen until the new one is ready.
+        // Chunks that aren't loaded yet pick up their edits when they are generated.
+        for chunk_coordinates in self.edits.dirty_chunks() {
+            let is_loaded = self.loaded_chunk(chunk_coordinates).is_some();
+            if !is_loaded || self.edited_chunks_in_queue.contains(&chunk_coordinates) {
+                continue;
+            }
+
+            let handle = Chunk::request_chunk_generation(
+                chunk_coordinates,
+                &self.coloring,
+                &self.noise_map_settings,
+                &self.mesh_settings,
+                &self.detail_levels,
+                self.neighbourhood(chunk_coordinates),
+                self.cache_handle(),
+            );
+            self.chunks_in_queue.push(handle);
+            self.edited_chunks_in_queue.insert(chunk_coordinates);
+            self.edits.mark_clean(chunk_coordinates);
+        }
+    }
+
+    // Whether chunks are still being generated, meshed again or uploaded
+    pub fn is_generating(&self) -> bool {
+        !self.chunks_in_queue.is_empty() || !self.upload_queue.is_empty()
+    }
+
+    pub fn edits(&self) -> &TerrainEdits {
+        &self.edits
+    }
+
+    pub fn clear_edits(&mut self) {
+        self.edits.clear();
+    }
+
+    // Applies one frame of a brush stroke centered on a point of the terrain
+    pub fn sculpt(&mut self, brush: &Brush, center: &glm::Vec3, delta_time: f32) {
+        let mut lookup = HeightLookup::new(self);
+        let changes = brush.apply(center, delta_time, |x, z| lookup.edited_height(x, z));
+
+        for ((x, z), change) in changes {
+            for (chunk_coordinates, grid_x, grid_z) in Chunk::shared_vertex_locations(x, z) {
+                self.edits.add(chunk_coordinates, grid_x, grid_z, change);
+
+                // Neighbours sample this chunk's edge for their ambient occlusion
+                let size = CHUNK_PIXEL_SIZE;
+                let x_offsets = [
+                    Some(0),
+                    (grid_x < BORDER_WIDTH).then_some(-1),
+                    (grid_x > size - BORDER_WIDTH).then_some(1),
+                ];
+                let z_offsets = [
+                    Some(0),
+                    (grid_z < BORDER_WIDTH).then_some(1),
+                    (grid_z > size - BORDER_WIDTH).then_some(-1),
+                ];
+                for x_offset in x_offsets.into_iter().flatten() {
+                    for z_offset in z_offsets.into_iter().flatten() {
+                        if (x_offset, z_offset) != (0, 0) {
+                            self.edits.mark_dirty((
+                                chunk_coordinates.0 + x_offset,
+                                chunk_coordinates.1 + z_offset,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
     }
 
     pub fn clear_chunk_container_for_update(&mut self, camera_position: glm::Vec3) {
@@ -134,35 +504,180 @@ impl ChunkContainer {
             (camera_position.x / self.chunk_size as f32).round() as i32,
             (camera_position.z / self.chunk_size as f32).round() as i32,
         );
+        self.center_chunk_coordinates = current_chunk_coordinates;
 
         for handle in self.chunks_in_queue.drain(..) {
             handle.join().unwrap();
         }
 
         self.chunks_in_queue.clear();
+        self.upload_queue.clear();
+        self.edited_chunks_in_queue.clear();
         self.current_visible_chunks.clear();
         self.chunk_map.clear();
+        // The vertex layout may have changed, and nothing is left in the old pool but the
+        // default chunk
+        self.mesh_pool = MeshPool::new(Mesh::terrain_vertex_layout(
+            self.mesh_settings.compact_vertices,
+        ));
+
+        // Every chunk is generated again with the current edits
+        self.edits.mark_all_clean();
 
         let mut new_default_chunk = Chunk::create_chunk(
             current_chunk_coordinates,
-            &self.materials,
+            &self.coloring,
             &self.noise_map_settings,
             &self.mesh_settings,
             &self.detail_levels,
+            &self.neighbourhood(current_chunk_coordinates),
+            &self.cache_handle(),
         );
-        new_default_chunk.rebind_vaos();
+        new_default_chunk.upload_meshes(&mut self.mesh_pool);
 
+        // Only ever a placeholder, the chunk at its position is generated like any other
         self.default_chunk = Rc::new(new_default_chunk);
+    }
 
-        self.chunk_map
-            .insert(current_chunk_coordinates, Rc::clone(&self.default_chunk));
-        self.current_visible_chunks
-            .push(Rc::clone(&self.default_chunk));
+    // Terrain height at world coordinates (x, z)
+    pub fn height_at(&self, x: f32, z: f32, query: &SurfaceQuery) -> f32 {
+        self.surface_at(x, z, query).0
     }
 
-    pub fn generate_scene(&mut self, shader_id: u32, camera_position: glm::Vec3) -> Vec<SceneNode> {
+    // Terrain surface normal at world coordinates (x, z)
+    pub fn normal_at(&self, x: f32, z: f32, query: &SurfaceQuery) -> glm::Vec3 {
+        self.surface_at(x, z, query).1
+    }
+
+    // Uses the height field of the loaded chunk under the point, and evaluates the noise
+    // directly where no chunk has been generated yet
+    fn surface_at(&self, x: f32, z: f32, query: &SurfaceQuery) -> (f32, glm::Vec3) {
+        // Neighbouring chunks share their border vertices, so they are placed
+        // CHUNK_PIXEL_SIZE apart
+        let chunk_coordinates = (
+            (x / CHUNK_PIXEL_SIZE as f32).round() as i32,
+            (z / CHUNK_PIXEL_SIZE as f32).round() as i32,
+        );
+
+        let half_chunk_size = CHUNK_PIXEL_SIZE as f32 / 2.0;
+        let grid_x = x - (chunk_coordinates.0 * CHUNK_PIXEL_SIZE) as f32 + half_chunk_size;
+        let grid_z = half_chunk_size - (z - (chunk_coordinates.1 * CHUNK_PIXEL_SIZE) as f32);
+
+        match self.loaded_chunk(chunk_coordinates) {
+            Some(chunk) => {
+                let height_field = &chunk.height_field;
+                height_field::surface_at(
+                    |x, z| height_field.height(x, z),
+                    height_field.size,
+                    grid_x,
+                    grid_z,
+                    query,
+                )
+            }
+            None => {
+                let mut lookup = HeightLookup::new(self);
+                height_field::surface_at(
+                    |x, z| {
+                        lookup.procedural_height(chunk_coordinates, x, z)
+                            + self.edits.delta(chunk_coordinates, x, z)
+                    },
+                    self.noise_map_settings.width as usize,
+                    grid_x,
+                    grid_z,
+                    query,
+                )
+            }
+        }
+    }
+
+    // First intersection of the ray with the full resolution terrain within max_distance
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+        let mut lookup = HeightLookup::new(self);
+        let (distance, normal) = raycast::raycast(
+            |x, z| lookup.height(x, z),
+            ray,
+            max_distance,
+            self.height_range(),
+        )?;
+        let position = ray.at(distance);
+
+        let (chunk, grid_x, grid_z) =
+            Chunk::vertex_location(position.x.round() as i32, position.z.round() as i32);
+        let material = self
+            .loaded_chunk(chunk)
+            .map(|loaded_chunk| loaded_chunk.material_at(grid_x as usize, grid_z as usize));
+
+        Some(RayHit {
+            position,
+            normal,
+            distance,
+            chunk,
+            material,
+        })
+    }
+
+    // Lowest and highest height the mesh curve, strength and edits can produce
+    fn height_range(&self) -> (f32, f32) {
+        const CURVE_SAMPLES: usize = 64;
+
+        let mut range = (f32::MAX, f32::MIN);
+        for sample in 0..=CURVE_SAMPLES {
+            let t = sample as f64 / CURVE_SAMPLES as f64;
+            let height = self.mesh_settings.curve.evaluate(t) as f32 * self.mesh_settings.strength;
+            range = (range.0.min(height), range.1.max(height));
+        }
+
+        let (lowest_delta, highest_delta) = self.edits.delta_range();
+        (range.0 + lowest_delta, range.1 + highest_delta)
+    }
+
+    // Triangle weighted ACMR of the meshes drawn for the visible chunks, before and after
+    // the vertex cache optimization
+    pub fn acmr_stats(&self) -> (f32, f32) {
+        let mut triangle_count = 0.0;
+        let mut acmr_before = 0.0;
+        let mut acmr_after = 0.0;
+
+        for chunk in self.current_visible_chunks.iter() {
+            let mesh = chunk.mesh(chunk.lod_index.get());
+            let triangles = mesh.indices.len() as f32 / 3.0;
+            triangle_count += triangles;
+            acmr_before += mesh.acmr_before * triangles;
+            acmr_after += mesh.acmr_after * triangles;
+        }
+
+        if triangle_count == 0.0 {
+            return (0.0, 0.0);
+        }
+        (acmr_before / triangle_count, acmr_after / triangle_count)
+    }
+
+    // The shared buffers the meshes of the loaded chunks are drawn from
+    pub fn mesh_pool(&mut self) -> &mut MeshPool {
+        &mut self.mesh_pool
+    }
+
+    pub fn generate_scene(
+        &mut self,
+        camera_position: glm::Vec3,
+        frustum: &Frustum,
+        max_distance: f32,
+    ) -> Vec<SceneNode> {
         let mut scene: Vec<SceneNode> = Vec::new();
+        self.chunks_drawn = 0;
+        self.chunks_culled = 0;
+
         for chunk in self.current_visible_chunks.iter() {
+            // Skip chunks outside the view and chunks hidden entirely by fog
+            let bounds = chunk.bounds();
+            if !frustum.intersects_aabb(&bounds)
+                || bounds.distance_to(&camera_position) > max_distance
+            {
+                self.chunks_culled += 1;
+                continue;
+            }
+            self.chunks_drawn += 1;
+
             let chunk_world_position = glm::vec3(
                 chunk.position.0 as f32 * self.chunk_size as f32,
                 0.0,
@@ -178,7 +693,8 @@ impl ChunkContainer {
                 }
             }
 
-            scene.push(chunk.get_scene_node(shader_id, lod_index));
+            chunk.lod_index.set(lod_index);
+            scene.push(chunk.get_scene_node(lod_index));
         }
         scene
     }
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use crate::{
+    height_field::ProceduralHeights,
+    mesh::mesh_settings::MeshSettings,
+    noise_map::{noise_map_settings::NoiseMapSettings, NoiseSampler},
+    sculpt::terrain_edits::TerrainEdits,
+    CHUNK_PIXEL_SIZE,
+};
+
+use super::chunk::Chunk;
+
+// What generating a chunk on another thread needs from around it: the edits of the chunk and
+// its eight neighbours, and the procedural heights of the neighbours that are loaded
+pub struct Neighbourhood {
+    pub edits: TerrainEdits,
+    procedural_heights: HashMap<(i32, i32), ProceduralHeights>,
+}
+
+impl Neighbourhood {
+    pub fn new(position: (i32, i32), edits: &TerrainEdits) -> Self {
+        Neighbourhood {
+            edits: edits.around(position),
+            procedural_heights: HashMap::new(),
+        }
+    }
+
+    pub fn add_loaded(&mut self, chunk: (i32, i32), procedural_heights: ProceduralHeights) {
+        self.procedural_heights.insert(chunk, procedural_heights);
+    }
+
+    // Edited heights at grid coordinates of the chunk at position that lie past its edges.
+    // Neighbours that aren't loaded are sampled from the noise.
+    pub fn border_heights<'a>(
+        &'a self,
+        position: (i32, i32),
+        noise_map_settings: &'a NoiseMapSettings,
+        mesh_settings: &'a MeshSettings,
+    ) -> impl FnMut(i32, i32) -> f32 + 'a {
+        let mut samplers: HashMap<(i32, i32), NoiseSampler> = HashMap::new();
+        move |grid_x, grid_z| {
+            let half_chunk_size = CHUNK_PIXEL_SIZE / 2;
+            let (chunk, x, z) = Chunk::vertex_location(
+                position.0 * CHUNK_PIXEL_SIZE + grid_x - half_chunk_size,
+                position.1 * CHUNK_PIXEL_SIZE + half_chunk_size - grid_z,
+            );
+
+            let procedural_height = match self.procedural_heights.get(&chunk) {
+                Some(heights) => heights.height(x, z),
+                None => {
+                    let sampler = samplers.entry(chunk).or_insert_with(|| {
+                        NoiseSampler::new(Chunk::noise_map_settings_at(chunk, noise_map_settings))
+                    });
+                    mesh_settings
+                        .curve
+                        .evaluate(sampler.sample(x as f64, z as f64)) as f32
+                        * mesh_settings.strength
+                }
+            };
+            procedural_height + self.edits.delta(chunk, x, z)
+        }
+    }
+}
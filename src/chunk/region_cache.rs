@@ -0,0 +1,446 @@
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    height_field::HeightField, mesh::mesh_settings::MeshSettings,
+    noise_map::noise_map_settings::NoiseMapSettings,
+};
+
+const REGION_MAGIC: &[u8; 4] = b"TRGN";
+// Bump whenever the layout of region files or of the chunk entries changes
+const REGION_FORMAT_VERSION: u32 = 1;
+
+// Chunks along each side of a region
+pub const REGION_SIZE: i32 = 8;
+const ENTRY_COUNT: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+// Magic, format version and region size, followed by the index table
+const HEADER_SIZE: u64 = 12;
+// Offset, compressed length and settings hash of one chunk
+const ENTRY_SIZE: u64 = 20;
+const INDEX_SIZE: u64 = ENTRY_SIZE * ENTRY_COUNT as u64;
+
+#[derive(Clone, Copy, Default)]
+struct IndexEntry {
+    offset: u64,
+    // Zero for chunks that aren't in the region file
+    length: u32,
+    settings_hash: u64,
+}
+
+// FNV-1a, which unlike the std hasher is stable between builds
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Fingerprint of the settings the cached noise heights depend on. The curve, strength and
+// materials are applied after loading, so changing them keeps the cache valid.
+pub fn settings_hash(noise_map_settings: &NoiseMapSettings) -> u64 {
+    let mut hash = 0xcbf29ce484222325;
+
+    hash = hash_bytes(hash, &noise_map_settings.width.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.height.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.scale.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.octaves.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.persistence.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.lacunarity.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.seed.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.offset_x.to_le_bytes());
+    hash = hash_bytes(hash, &noise_map_settings.offset_y.to_le_bytes());
+
+    hash
+}
+
+// The cache as seen by a chunk request, with the settings hash current when the chunk was
+// requested
+#[derive(Clone)]
+pub struct CacheHandle {
+    pub cache: Arc<Mutex<RegionCache>>,
+    pub settings_hash: u64,
+}
+
+// On disk cache of generated height fields, grouping REGION_SIZE x REGION_SIZE chunks per file.
+// Each region file starts with an index table pointing at zlib compressed chunk entries,
+// which are tagged with the settings hash they were generated with. Loading and storing take
+// the hash the chunk was requested with, since chunks still generating when the settings
+// change belong to the old settings.
+pub struct RegionCache {
+    directory: PathBuf,
+    // The current settings, prune drops the entries of any others
+    settings_hash: u64,
+
+    // Chunks loaded from and written to the cache since startup
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl RegionCache {
+    // Nothing matches until the settings hash is set
+    pub fn new(directory: &str) -> Self {
+        RegionCache {
+            directory: PathBuf::from(directory),
+            settings_hash: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn set_settings_hash(&mut self, settings_hash: u64) {
+        self.settings_hash = settings_hash;
+    }
+
+    fn region_path(&self, region: (i32, i32)) -> PathBuf {
+        self.directory
+            .join(format!("r.{}.{}.region", region.0, region.1))
+    }
+
+    // The region holding a chunk, and the chunk's slot in the region's index table
+    fn locate(chunk: (i32, i32)) -> ((i32, i32), usize) {
+        let region = (
+            chunk.0.div_euclid(REGION_SIZE),
+            chunk.1.div_euclid(REGION_SIZE),
+        );
+        let slot = chunk.1.rem_euclid(REGION_SIZE) * REGION_SIZE + chunk.0.rem_euclid(REGION_SIZE);
+        (region, slot as usize)
+    }
+
+    // None when the file isn't a region file of the current format
+    fn read_index(file: &mut fs::File) -> Option<Vec<IndexEntry>> {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        file.seek(SeekFrom::Start(0)).ok()?;
+        file.read_exact(&mut header).ok()?;
+
+        let word =
+            |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        if &header[0..4] != REGION_MAGIC
+            || word(4) != REGION_FORMAT_VERSION
+            || word(8) != REGION_SIZE as u32
+        {
+            return None;
+        }
+
+        let mut table = vec![0u8; INDEX_SIZE as usize];
+        file.read_exact(&mut table).ok()?;
+
+        Some(
+            table
+                .chunks_exact(ENTRY_SIZE as usize)
+                .map(|entry| IndexEntry {
+                    offset: u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                    length: u32::from_le_bytes(entry[8..12].try_into().unwrap()),
+                    settings_hash: u64::from_le_bytes(entry[12..20].try_into().unwrap()),
+                })
+                .collect(),
+        )
+    }
+
+    fn write_header(file: &mut fs::File, index: &[IndexEntry]) -> std::io::Result<()> {
+        let mut bytes: Vec<u8> = Vec::with_capacity((HEADER_SIZE + INDEX_SIZE) as usize);
+        bytes.extend(REGION_MAGIC);
+        bytes.extend(REGION_FORMAT_VERSION.to_le_bytes());
+        bytes.extend((REGION_SIZE as u32).to_le_bytes());
+        for entry in index {
+            bytes.extend(entry.offset.to_le_bytes());
+            bytes.extend(entry.length.to_le_bytes());
+            bytes.extend(entry.settings_hash.to_le_bytes());
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&bytes)
+    }
+
+    // Only the noise heights are stored, the heights follow from the mesh settings. The bytes
+    // of the floats are grouped by significance first, which compresses far better.
+    pub fn encode(height_field: &HeightField) -> std::io::Result<Vec<u8>> {
+        let count = height_field.noise_heights.len();
+        let mut shuffled = vec![0u8; count * 4];
+        for (index, value) in height_field.noise_heights.iter().enumerate() {
+            for (byte_index, byte) in value.to_le_bytes().into_iter().enumerate() {
+                shuffled[byte_index * count + index] = byte;
+            }
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&(height_field.size as u32).to_le_bytes())?;
+        encoder.write_all(&shuffled)?;
+        encoder.finish()
+    }
+
+    pub fn decode(data: &[u8], mesh_settings: &MeshSettings) -> Option<HeightField> {
+        let mut bytes = Vec::new();
+        ZlibDecoder::new(data).read_to_end(&mut bytes).ok()?;
+
+        let size = u32::from_le_bytes(bytes.get(0..4)?.try_into().unwrap()) as usize;
+        let shuffled = &bytes[4..];
+        let count = size * size;
+        if shuffled.len() != count * 4 {
+            return None;
+        }
+
+        let noise_heights: Vec<f32> = (0..count)
+            .map(|index| {
+                f32::from_le_bytes([
+                    shuffled[index],
+                    shuffled[count + index],
+                    shuffled[2 * count + index],
+                    shuffled[3 * count + index],
+                ])
+            })
+            .collect();
+        let heights: Vec<f32> = noise_heights
+            .iter()
+            .map(|noise_height| {
+                mesh_settings.curve.evaluate(*noise_height as f64) as f32 * mesh_settings.strength
+            })
+            .collect();
+
+        Some(HeightField {
+            size,
+            noise_heights,
+            heights: Arc::new(heights),
+            edits: None,
+            occlusion: Vec::new(),
+        })
+    }
+
+    // The encoded height field of a chunk, if it was cached with the given settings. Decoding
+    // is left to the caller, so it doesn't hold up other threads waiting for the cache.
+    pub fn load(&mut self, chunk: (i32, i32), settings_hash: u64) -> Option<Vec<u8>> {
+        let (region, slot) = RegionCache::locate(chunk);
+        let data = fs::File::open(self.region_path(region))
+            .ok()
+            .and_then(|mut file| {
+                let entry = RegionCache::read_index(&mut file)?[slot];
+                if entry.length == 0 || entry.settings_hash != settings_hash {
+                    return None;
+                }
+
+                let mut data = vec![0u8; entry.length as usize];
+                file.seek(SeekFrom::Start(entry.offset)).ok()?;
+                file.read_exact(&mut data).ok()?;
+                Some(data)
+            });
+
+        match data {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+        data
+    }
+
+    // Appends an encoded height field to its region file. The space of the entry it replaces
+    // is only reclaimed by prune.
+    pub fn store(
+        &mut self,
+        chunk: (i32, i32),
+        settings_hash: u64,
+        data: &[u8],
+    ) -> Result<(), String> {
+        let (region, slot) = RegionCache::locate(chunk);
+        let path = self.region_path(region);
+        let error = |e: std::io::Error| format!("Could not write {}: {}", path.display(), e);
+
+        fs::create_dir_all(&self.directory).map_err(error)?;
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(error)?;
+
+        // Files from another format version are started over
+        let mut index = match RegionCache::read_index(&mut file) {
+            Some(index) => index,
+            None => {
+                file.set_len(0).map_err(error)?;
+                vec![IndexEntry::default(); ENTRY_COUNT]
+            }
+        };
+
+        let offset = file
+            .seek(SeekFrom::End(0))
+            .map_err(error)?
+            .max(HEADER_SIZE + INDEX_SIZE);
+        file.seek(SeekFrom::Start(offset)).map_err(error)?;
+        file.write_all(data).map_err(error)?;
+
+        index[slot] = IndexEntry {
+            offset,
+            length: data.len() as u32,
+            settings_hash,
+        };
+        RegionCache::write_header(&mut file, &index).map_err(error)
+    }
+
+    // Rewrites every region file without the entries of other settings and the space of
+    // replaced entries, returning how many entries were dropped
+    pub fn prune(&mut self) -> Result<usize, String> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(0),
+        };
+
+        let mut dropped = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != "region")
+            {
+                continue;
+            }
+            dropped += self
+                .prune_region(&path)
+                .map_err(|e| format!("Could not prune {}: {}", path.display(), e))?;
+        }
+        Ok(dropped)
+    }
+
+    fn prune_region(&self, path: &Path) -> std::io::Result<usize> {
+        let mut file = fs::File::open(path)?;
+        let index = match RegionCache::read_index(&mut file) {
+            Some(index) => index,
+            None => {
+                fs::remove_file(path)?;
+                return Ok(1);
+            }
+        };
+
+        let mut dropped = 0;
+        let mut new_index = vec![IndexEntry::default(); ENTRY_COUNT];
+        let mut data: Vec<u8> = Vec::new();
+
+        for (slot, entry) in index.iter().enumerate() {
+            if entry.length == 0 {
+                continue;
+            }
+            if entry.settings_hash != self.settings_hash {
+                dropped += 1;
+                continue;
+            }
+
+            let mut chunk_data = vec![0u8; entry.length as usize];
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut chunk_data)?;
+
+            new_index[slot] = IndexEntry {
+                offset: HEADER_SIZE + INDEX_SIZE + data.len() as u64,
+                ..*entry
+            };
+            data.extend(chunk_data);
+        }
+
+        if data.is_empty() {
+            fs::remove_file(path)?;
+            return Ok(dropped);
+        }
+
+        // Write next to the region and swap it in, so a failure leaves the old file intact
+        let temporary_path = path.with_extension("region.tmp");
+        let mut new_file = fs::File::create(&temporary_path)?;
+        RegionCache::write_header(&mut new_file, &new_index)?;
+        new_file.write_all(&data)?;
+        fs::rename(&temporary_path, path)?;
+
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::Arc};
+
+    use crate::{
+        curve_editor::curve::Curve, height_field::HeightField, mesh::mesh_settings::MeshSettings,
+    };
+
+    use super::{RegionCache, HEADER_SIZE, INDEX_SIZE};
+
+    const CURRENT: u64 = 1;
+    const OLD: u64 = 2;
+
+    fn temporary_directory(name: &str) -> String {
+        let directory = env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory.to_string_lossy().into_owned()
+    }
+
+    fn height_field(noise_heights: Vec<f32>) -> HeightField {
+        HeightField {
+            size: 3,
+            heights: Arc::new(vec![0.0; noise_heights.len()]),
+            noise_heights,
+            edits: None,
+            occlusion: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let noise_heights = vec![0.0, 0.1, 0.25, 0.5, 0.75, 1.0, 0.05, 0.333, 0.9];
+        let mesh_settings = MeshSettings::new("Mesh".to_string(), 10.0, Curve::quadratic(), 0);
+
+        let data = RegionCache::encode(&height_field(noise_heights.clone())).unwrap();
+        let decoded = RegionCache::decode(&data, &mesh_settings).unwrap();
+
+        assert_eq!(decoded.size, 3);
+        assert_eq!(decoded.noise_heights, noise_heights);
+        for (height, noise_height) in decoded.heights.iter().zip(&noise_heights) {
+            let expected = mesh_settings.curve.evaluate(*noise_height as f64) as f32 * 10.0;
+            assert_eq!(*height, expected);
+        }
+        assert!(RegionCache::decode(&data[..data.len() - 1], &mesh_settings).is_none());
+    }
+
+    #[test]
+    fn store_load_overwrite_and_prune() {
+        let directory = temporary_directory("region_cache_test");
+        let mut cache = RegionCache::new(&directory);
+        cache.set_settings_hash(CURRENT);
+        let region_path = cache.region_path((0, 0));
+
+        cache.store((1, 2), CURRENT, b"first").unwrap();
+        assert_eq!(cache.load((1, 2), CURRENT), Some(b"first".to_vec()));
+        // Other settings, an empty slot of the same region and a region without a file
+        assert_eq!(cache.load((1, 2), OLD), None);
+        assert_eq!(cache.load((2, 2), CURRENT), None);
+        assert_eq!(cache.load((-1, -1), CURRENT), None);
+        assert_eq!((cache.hits, cache.misses), (1, 3));
+
+        // Chunks left of and below the origin land in their own region
+        cache.store((-1, -1), CURRENT, b"negative").unwrap();
+        assert_eq!(cache.load((-1, -1), CURRENT), Some(b"negative".to_vec()));
+
+        // Overwriting appends, the old entry's space stays until pruned
+        cache.store((1, 2), CURRENT, b"second").unwrap();
+        assert_eq!(cache.load((1, 2), CURRENT), Some(b"second".to_vec()));
+        let region_size = fs::metadata(&region_path).unwrap().len();
+        assert_eq!(region_size, HEADER_SIZE + INDEX_SIZE + 11);
+
+        cache.store((2, 2), OLD, b"stale").unwrap();
+        assert_eq!(cache.prune(), Ok(1));
+
+        assert_eq!(cache.load((1, 2), CURRENT), Some(b"second".to_vec()));
+        assert_eq!(cache.load((2, 2), OLD), None);
+        assert_eq!(cache.load((-1, -1), CURRENT), Some(b"negative".to_vec()));
+        let region_size = fs::metadata(&region_path).unwrap().len();
+        assert_eq!(region_size, HEADER_SIZE + INDEX_SIZE + 6);
+
+        // A region left with nothing current is removed
+        cache.set_settings_hash(OLD);
+        assert_eq!(cache.prune(), Ok(2));
+        assert!(!region_path.exists());
+        assert_eq!(cache.load((-1, -1), CURRENT), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
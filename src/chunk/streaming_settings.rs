@@ -0,0 +1,33 @@
+use imgui::{CollapsingHeader, Ui};
+
+#[derive(Clone, PartialEq)]
+pub struct StreamingSettings {
+    pub name: String,
+    // Mesh data uploaded to the GPU per frame. At least one level of detail goes up every
+    // frame, so chunks keep arriving with budgets below the size of a single mesh.
+    pub upload_budget_megabytes: f32,
+}
+
+impl StreamingSettings {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            upload_budget_megabytes: 16.0,
+        }
+    }
+
+    pub fn upload_budget(&self) -> usize {
+        (self.upload_budget_megabytes * 1024.0 * 1024.0) as usize
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.slider(
+                "Upload budget (MB)",
+                1.0,
+                64.0,
+                &mut self.upload_budget_megabytes,
+            );
+        }
+    }
+}
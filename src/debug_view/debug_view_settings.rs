@@ -0,0 +1,95 @@
+use imgui::{CollapsingHeader, Ui};
+
+use crate::chunk::LoadState;
+
+use super::load_state_color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    Shaded,
+    // The lit terrain drawn as polygon outlines
+    Wireframe,
+    // World space normals as colours, without lighting
+    Normals,
+    // Each chunk tinted by the level of detail it is drawn with
+    LevelOfDetail,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 4] = [
+        DebugView::Shaded,
+        DebugView::Wireframe,
+        DebugView::Normals,
+        DebugView::LevelOfDetail,
+    ];
+
+    // The DEBUG_VIEW_* defines of shape.frag, and the index of the view's permutation
+    pub fn shader_index(self) -> i32 {
+        match self {
+            DebugView::Shaded => 0,
+            DebugView::Wireframe => 1,
+            DebugView::Normals => 2,
+            DebugView::LevelOfDetail => 3,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct DebugViewSettings {
+    pub name: String,
+    pub view: DebugView,
+    // Chunk bounds coloured by whether the chunk is a placeholder, generated or being meshed
+    // again
+    pub chunk_borders: bool,
+    pub normal_lines: bool,
+    pub normal_line_length: f32,
+    // Normal lines are only drawn for vertices closer to the camera than this
+    pub normal_line_distance: f32,
+}
+
+impl DebugViewSettings {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            view: DebugView::Shaded,
+            chunk_borders: false,
+            normal_lines: false,
+            normal_line_length: 2.0,
+            normal_line_distance: 100.0,
+        }
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.radio_button("Shaded", &mut self.view, DebugView::Shaded);
+            ui.same_line();
+            ui.radio_button("Wireframe", &mut self.view, DebugView::Wireframe);
+            ui.same_line();
+            ui.radio_button("Normals", &mut self.view, DebugView::Normals);
+            ui.same_line();
+            ui.radio_button("LOD", &mut self.view, DebugView::LevelOfDetail);
+
+            ui.checkbox("Chunk borders", &mut self.chunk_borders);
+            if self.chunk_borders {
+                let legend = [
+                    (LoadState::Generated, "Generated"),
+                    (LoadState::Placeholder, "Placeholder"),
+                    (LoadState::Remeshing, "Meshing edits"),
+                ];
+                for (index, (state, label)) in legend.into_iter().enumerate() {
+                    if index > 0 {
+                        ui.same_line();
+                    }
+                    let color = load_state_color(state);
+                    ui.text_colored([color.x, color.y, color.z, 1.0], label);
+                }
+            }
+
+            ui.checkbox("Normal lines", &mut self.normal_lines);
+            if self.normal_lines {
+                ui.slider("Line length", 0.1, 10.0, &mut self.normal_line_length);
+                ui.slider("Line distance", 10.0, 600.0, &mut self.normal_line_distance);
+            }
+        }
+    }
+}
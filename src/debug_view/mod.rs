@@ -0,0 +1,136 @@
+pub mod debug_view_settings;
+
+use crate::{
+    aabb::Aabb,
+    chunk::LoadState,
+    mesh::mesh_pool::MeshPool,
+    scenenode::SceneNode,
+    shader::Shader,
+    utils,
+    vertex_layout::{AttributeType, VertexAttribute, VertexLayout},
+};
+
+use self::debug_view_settings::DebugViewSettings;
+
+pub fn load_state_color(state: LoadState) -> glm::Vec3 {
+    match state {
+        LoadState::Placeholder => glm::vec3(1.0, 0.2, 0.2),
+        LoadState::Generated => glm::vec3(0.2, 1.0, 0.3),
+        LoadState::Remeshing => glm::vec3(1.0, 0.85, 0.2),
+    }
+}
+
+// Line overlays for looking at chunk loading and the terrain normals
+pub struct DebugOverlay {
+    pub line_shader: Shader,
+    // Expands the vertices of the terrain's own vertex arrays into lines
+    pub normal_line_shader: Shader,
+
+    layout: VertexLayout,
+    // Refilled with the lines of every frame
+    vao_id: u32,
+    vbo_id: u32,
+}
+
+impl DebugOverlay {
+    pub fn new(line_shader: Shader, normal_line_shader: Shader) -> Self {
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::new("position", 0, 3, AttributeType::Float, false),
+            VertexAttribute::new("color", 1, 3, AttributeType::Float, false),
+        ]);
+
+        let mut vao_id: u32 = 0;
+        let mut vbo_id: u32 = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao_id);
+            gl::BindVertexArray(vao_id);
+            gl::GenBuffers(1, &mut vbo_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            layout.apply();
+            gl::BindVertexArray(0);
+        }
+
+        DebugOverlay {
+            line_shader,
+            normal_line_shader,
+            layout,
+            vao_id,
+            vbo_id,
+        }
+    }
+
+    // Outlines the bounds of the chunks in the colour of their load state
+    pub fn draw_chunk_borders(
+        &self,
+        load_states: &[(Aabb, LoadState)],
+        view_projection_matrix: &glm::Mat4,
+    ) {
+        let mut vertex_data: Vec<u8> = Vec::new();
+        let mut vertex_count = 0;
+
+        for (bounds, state) in load_states {
+            let color = load_state_color(*state);
+            // Bit i of a corner's index picks the min or max along axis i
+            let extremes = [bounds.min, bounds.max];
+            let corner = |index: usize| {
+                glm::vec3(
+                    extremes[index & 1].x,
+                    extremes[(index >> 1) & 1].y,
+                    extremes[(index >> 2) & 1].z,
+                )
+            };
+
+            // Every pair of corners that differ along exactly one axis is an edge
+            for from in 0..8 {
+                for axis in [1, 2, 4] {
+                    if from & axis != 0 {
+                        continue;
+                    }
+                    for point in [corner(from), corner(from | axis)] {
+                        self.layout
+                            .push_vertex(&mut vertex_data, &[point.as_slice(), color.as_slice()]);
+                        vertex_count += 1;
+                    }
+                }
+            }
+        }
+
+        if vertex_count == 0 {
+            return;
+        }
+        unsafe {
+            self.line_shader.activate();
+            gl::BindVertexArray(self.vao_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                utils::byte_size_of_array(&vertex_data),
+                utils::pointer_to_array(&vertex_data),
+                gl::STREAM_DRAW,
+            );
+            self.line_shader
+                .set_mat4("transform_matrix", view_projection_matrix);
+            gl::DrawArrays(gl::LINES, 0, vertex_count);
+        }
+    }
+
+    // Draws a line along the normal of every vertex of the nodes near the camera
+    pub fn draw_normal_lines(
+        &self,
+        mesh_pool: &mut MeshPool,
+        nodes: &[SceneNode],
+        view_projection_matrix: &glm::Mat4,
+        camera_position: &glm::Vec3,
+        settings: &DebugViewSettings,
+    ) {
+        unsafe {
+            let shader = &self.normal_line_shader;
+            shader.activate();
+            shader.set_mat4("view_projection_matrix", view_projection_matrix);
+            shader.set_vec3("camera_position", camera_position);
+            shader.set_f32("line_length", settings.normal_line_length);
+            shader.set_f32("max_distance", settings.normal_line_distance);
+        }
+        mesh_pool.draw_points(nodes);
+    }
+}
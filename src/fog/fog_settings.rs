@@ -0,0 +1,110 @@
+use imgui::{CollapsingHeader, Ui};
+
+use crate::shader::Shader;
+
+// Fog reaching this amount counts as fully fogged
+const OPAQUE_FOG: f32 = 0.995;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogMode {
+    Off,
+    // Grows evenly from the start to the end distance
+    Linear,
+    // Grows with distance like light absorbed by an even medium
+    Exponential,
+    // Exponential fog that thins out with height, collecting in valleys
+    Height,
+}
+
+impl FogMode {
+    // The FOG_* defines of fog.glsl
+    pub fn shader_index(self) -> i32 {
+        match self {
+            FogMode::Off => 0,
+            FogMode::Linear => 1,
+            FogMode::Exponential => 2,
+            FogMode::Height => 3,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct FogSettings {
+    pub name: String,
+    pub mode: FogMode,
+    // Linear fog start and end as fractions of the view distance
+    pub start: f32,
+    pub end: f32,
+    // At 1 exponential fog is fully opaque at the view distance
+    pub density: f32,
+    // How quickly height fog thins out per world unit above its base
+    pub height_falloff: f32,
+    pub base_height: f32,
+}
+
+impl FogSettings {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            mode: FogMode::Linear,
+            start: 0.6,
+            end: 0.98,
+            density: 1.0,
+            height_falloff: 0.1,
+            base_height: 0.0,
+        }
+    }
+
+    // Exponential fog coefficient per world unit
+    fn extinction(&self, view_distance: f32) -> f32 {
+        self.density * -(1.0 - OPAQUE_FOG).ln() / view_distance
+    }
+
+    // Distance beyond which nothing can be seen through the fog, if there is one. Height fog
+    // thins out above its base, so terrain behind it may always show.
+    pub fn opaque_distance(&self, view_distance: f32) -> Option<f32> {
+        match self.mode {
+            FogMode::Off | FogMode::Height => None,
+            FogMode::Linear => Some(self.end.max(self.start) * view_distance),
+            FogMode::Exponential => Some(view_distance / self.density.max(1e-3)),
+        }
+    }
+
+    // Sets the fog uniforms of a program that includes fog.glsl
+    pub fn apply(&self, shader: &Shader, view_distance: f32) {
+        shader.set_i32("fog_mode", self.mode.shader_index());
+        shader.set_f32("fog_start", self.start * view_distance);
+        shader.set_f32("fog_end", self.end.max(self.start + 1e-3) * view_distance);
+        shader.set_f32("fog_density", self.extinction(view_distance));
+        shader.set_f32("fog_height_falloff", self.height_falloff);
+        shader.set_f32("fog_base_height", self.base_height);
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.radio_button("Off", &mut self.mode, FogMode::Off);
+            ui.same_line();
+            ui.radio_button("Linear", &mut self.mode, FogMode::Linear);
+            ui.same_line();
+            ui.radio_button("Exponential", &mut self.mode, FogMode::Exponential);
+            ui.same_line();
+            ui.radio_button("Height", &mut self.mode, FogMode::Height);
+
+            match self.mode {
+                FogMode::Off => {}
+                FogMode::Linear => {
+                    ui.slider("Fog start", 0.0, 1.0, &mut self.start);
+                    ui.slider("Fog end", 0.0, 1.0, &mut self.end);
+                }
+                FogMode::Exponential => {
+                    ui.slider("Fog density", 0.1, 10.0, &mut self.density);
+                }
+                FogMode::Height => {
+                    ui.slider("Fog density", 0.1, 10.0, &mut self.density);
+                    ui.slider("Height falloff", 0.001, 1.0, &mut self.height_falloff);
+                    ui.slider("Fog base height", -20.0, 50.0, &mut self.base_height);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1 @@
+pub mod fog_settings;
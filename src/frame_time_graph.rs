@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+use imgui::Ui;
+
+const FRAME_COUNT: usize = 240;
+
+// The duration of the last frames next to the chunk data each uploaded, so hitches while
+// streaming can be told apart from others
+pub struct FrameTimeGraph {
+    frame_times: VecDeque<f32>,
+    uploaded_megabytes: VecDeque<f32>,
+}
+
+impl Default for FrameTimeGraph {
+    fn default() -> Self {
+        FrameTimeGraph {
+            frame_times: VecDeque::from(vec![0.0; FRAME_COUNT]),
+            uploaded_megabytes: VecDeque::from(vec![0.0; FRAME_COUNT]),
+        }
+    }
+}
+
+impl FrameTimeGraph {
+    // Adds a frame of delta_time seconds that uploaded bytes_uploaded of mesh data
+    pub fn push(&mut self, delta_time: f32, bytes_uploaded: usize) {
+        self.frame_times.pop_front();
+        self.frame_times.push_back(delta_time * 1000.0);
+        self.uploaded_megabytes.pop_front();
+        self.uploaded_megabytes
+            .push_back(bytes_uploaded as f32 / (1024.0 * 1024.0));
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        let frame_times = self.frame_times.make_contiguous();
+        let longest = frame_times.iter().copied().fold(0.0, f32::max);
+        // Scaled to at least two frames at 60 Hz, so a steady frame rate reads as a flat line
+        ui.plot_lines("##frame_times", frame_times)
+            .overlay_text(format!(
+                "Frame time: {:.1} ms, longest {:.1} ms",
+                frame_times[FRAME_COUNT - 1],
+                longest
+            ))
+            .scale_min(0.0)
+            .scale_max(longest.max(1000.0 / 30.0))
+            .graph_size([0.0, 60.0])
+            .build();
+
+        let uploaded_megabytes = self.uploaded_megabytes.make_contiguous();
+        let most = uploaded_megabytes.iter().copied().fold(0.0, f32::max);
+        ui.plot_histogram("##uploads", uploaded_megabytes)
+            .overlay_text(format!("Uploaded: most {:.1} MB in a frame", most))
+            .scale_min(0.0)
+            .scale_max(most.max(1.0))
+            .graph_size([0.0, 40.0])
+            .build();
+    }
+}
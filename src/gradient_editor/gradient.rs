@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: [f32; 3],
+}
+
+impl ColorStop {
+    pub fn new(position: f32, color: [f32; 3]) -> Self {
+        Self { position, color }
+    }
+}
+
+// Stops are kept in editing order, so they have to be sorted before sampling
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Gradient {
+    pub stops: Vec<ColorStop>,
+
+    #[serde(skip)]
+    pub selected_stop: usize,
+}
+
+impl PartialEq for Gradient {
+    fn eq(&self, other: &Self) -> bool {
+        self.stops == other.stops
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self::terrain()
+    }
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<ColorStop>) -> Self {
+        Gradient {
+            stops,
+            selected_stop: 0,
+        }
+    }
+
+    pub fn terrain() -> Self {
+        Gradient::new(vec![
+            ColorStop::new(0.0, [0.05, 0.1, 0.4]),
+            ColorStop::new(0.38, [0.267, 0.322, 0.722]),
+            ColorStop::new(0.42, [0.8, 0.8, 0.4]),
+            ColorStop::new(0.5, [0.35, 0.7, 0.3]),
+            ColorStop::new(0.7, [0.2, 0.45, 0.2]),
+            ColorStop::new(0.8, [0.45, 0.42, 0.4]),
+            ColorStop::new(0.9, [1.0, 1.0, 1.0]),
+        ])
+    }
+
+    pub fn sorted(&self) -> Gradient {
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Gradient::new(stops)
+    }
+
+    // Expects the stops to be sorted by position
+    pub fn evaluate(&self, t: f32) -> [f32; 3] {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [1.0, 1.0, 1.0],
+        };
+
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+
+        for pair in self.stops.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if t <= to.position {
+                let span = to.position - from.position;
+                let amount = if span > 0.0 {
+                    (t - from.position) / span
+                } else {
+                    1.0
+                };
+                return [
+                    from.color[0] + (to.color[0] - from.color[0]) * amount,
+                    from.color[1] + (to.color[1] - from.color[1]) * amount,
+                    from.color[2] + (to.color[2] - from.color[2]) * amount,
+                ];
+            }
+        }
+
+        last.color
+    }
+}
@@ -0,0 +1,132 @@
+use imgui::{ImString, MouseButton, Ui};
+
+use super::gradient::{ColorStop, Gradient};
+
+pub struct GradientEditor {
+    name: ImString,
+}
+
+impl GradientEditor {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: ImString::new(name),
+        }
+    }
+
+    // Click the bar to add a stop, drag a marker to move it and right click a marker to delete it
+    pub fn render(&self, ui: &Ui, gradient: &mut Gradient) {
+        ui.text(&self.name);
+
+        let draw_list = ui.get_window_draw_list();
+
+        let o: [f32; 2] = ui.cursor_screen_pos();
+        let ws = ui.content_region_avail();
+
+        let bar_height: f32 = 20.0;
+        let marker_size: f32 = 8.0;
+        let width = ws[0];
+
+        let bar_top_left = [o[0], o[1]];
+        let bar_bottom_right = [o[0] + width, o[1] + bar_height];
+
+        //draw gradient bar, one quad per segment between samples
+        let sorted = gradient.sorted();
+        let segments = 64;
+        for segment in 0..segments {
+            let t0 = segment as f32 / segments as f32;
+            let t1 = (segment + 1) as f32 / segments as f32;
+            let c0 = sorted.evaluate(t0);
+            let c1 = sorted.evaluate(t1);
+            let c0 = [c0[0], c0[1], c0[2], 1.0];
+            let c1 = [c1[0], c1[1], c1[2], 1.0];
+
+            draw_list.add_rect_filled_multicolor(
+                [bar_top_left[0] + width * t0, bar_top_left[1]],
+                [bar_top_left[0] + width * t1, bar_bottom_right[1]],
+                c0,
+                c1,
+                c1,
+                c0,
+            );
+        }
+        draw_list
+            .add_rect(bar_top_left, bar_bottom_right, [1.0, 1.0, 1.0, 1.0])
+            .build();
+
+        ui.set_cursor_screen_pos(bar_top_left);
+        if ui.invisible_button("##gradient_bar", [width, bar_height]) {
+            let t = ((ui.io().mouse_pos[0] - bar_top_left[0]) / width).clamp(0.0, 1.0);
+            let color = sorted.evaluate(t);
+            gradient.stops.push(ColorStop::new(t, color));
+            gradient.selected_stop = gradient.stops.len() - 1;
+        }
+
+        let mut deleted: Option<usize> = None;
+
+        for (index, stop) in gradient.stops.iter_mut().enumerate() {
+            let x = bar_top_left[0] + width * stop.position;
+            let y = bar_bottom_right[1];
+
+            ui.set_cursor_screen_pos([x - marker_size / 2.0, y]);
+            ui.invisible_button(format!("##stop{}", index), [marker_size, marker_size]);
+
+            if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+                stop.position = ((ui.io().mouse_pos[0] - bar_top_left[0]) / width).clamp(0.0, 1.0);
+            }
+            if ui.is_item_clicked() {
+                gradient.selected_stop = index;
+            }
+            if ui.is_item_clicked_with_button(MouseButton::Right) {
+                deleted = Some(index);
+            }
+
+            let outline = if index == gradient.selected_stop {
+                [1.0, 1.0, 0.0, 1.0]
+            } else {
+                [1.0, 1.0, 1.0, 1.0]
+            };
+            let fill = [stop.color[0], stop.color[1], stop.color[2], 1.0];
+
+            draw_list
+                .add_triangle(
+                    [x, y],
+                    [x - marker_size / 2.0, y + marker_size],
+                    [x + marker_size / 2.0, y + marker_size],
+                    fill,
+                )
+                .filled(true)
+                .build();
+            draw_list
+                .add_triangle(
+                    [x, y],
+                    [x - marker_size / 2.0, y + marker_size],
+                    [x + marker_size / 2.0, y + marker_size],
+                    outline,
+                )
+                .build();
+        }
+
+        // A gradient needs at least two stops to blend between
+        if let Some(index) = deleted {
+            if gradient.stops.len() > 2 {
+                gradient.stops.remove(index);
+            }
+        }
+        gradient.selected_stop = gradient
+            .selected_stop
+            .min(gradient.stops.len().saturating_sub(1));
+
+        //move cursor for next widget
+        ui.set_cursor_screen_pos([o[0], bar_bottom_right[1] + marker_size + 5.0]);
+
+        if let Some(stop) = gradient.stops.get_mut(gradient.selected_stop) {
+            ui.slider(
+                format!("Position##{}", self.name),
+                0.0,
+                1.0,
+                &mut stop.position,
+            );
+            ui.color_edit3(format!("Color##{}", self.name), &mut stop.color);
+        }
+    }
+}
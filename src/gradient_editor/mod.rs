@@ -0,0 +1,2 @@
+pub mod gradient;
+pub mod gradient_widget;
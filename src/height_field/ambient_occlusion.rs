@@ -0,0 +1,75 @@
+use super::HeightField;
+
+// Directions the horizon is searched in around every vertex
+const HORIZON_DIRECTIONS: usize = 8;
+
+// Grid distances of the horizon samples along each direction, denser close to the vertex
+const HORIZON_SAMPLE_DISTANCES: [f32; 7] = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+// Width of the ring of heights around a chunk its occlusion depends on. Edits this close to a
+// border change the occlusion of the chunk on the other side.
+pub const BORDER_WIDTH: i32 = HORIZON_SAMPLE_DISTANCES[HORIZON_SAMPLE_DISTANCES.len() - 1] as i32;
+
+// Sky visibility of every vertex of the height field, 1 where the whole sky is visible and
+// lower where the surrounding terrain rises above the horizon. Heights up to BORDER_WIDTH past
+// the edges come from border_height, which reads them from the neighbouring chunks.
+pub fn bake(
+    height_field: &HeightField,
+    mut border_height: impl FnMut(i32, i32) -> f32,
+) -> Vec<f32> {
+    let size = height_field.size as i32;
+    let margin = BORDER_WIDTH;
+    let padded_size = size + 2 * margin;
+
+    // The chunk's heights, surrounded by a ring of the neighbours' heights
+    let mut padded_heights = Vec::with_capacity((padded_size * padded_size) as usize);
+    for z in -margin..size + margin {
+        for x in -margin..size + margin {
+            let inside = (0..size).contains(&x) && (0..size).contains(&z);
+            padded_heights.push(if inside {
+                height_field.height(x, z)
+            } else {
+                border_height(x, z)
+            });
+        }
+    }
+    let padded_height =
+        |x: i32, z: i32| padded_heights[((z + margin) * padded_size + x + margin) as usize];
+
+    // Whole grid offsets of every horizon sample and their true distances
+    let samples: Vec<Vec<(i32, i32, f32)>> = (0..HORIZON_DIRECTIONS)
+        .map(|direction| {
+            let angle = direction as f32 / HORIZON_DIRECTIONS as f32 * std::f32::consts::TAU;
+            HORIZON_SAMPLE_DISTANCES
+                .iter()
+                .map(|distance| {
+                    let offset_x = (angle.cos() * distance).round() as i32;
+                    let offset_z = (angle.sin() * distance).round() as i32;
+                    let true_distance = ((offset_x * offset_x + offset_z * offset_z) as f32).sqrt();
+                    (offset_x, offset_z, true_distance)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut visibility = Vec::with_capacity((size * size) as usize);
+    for z in 0..size {
+        for x in 0..size {
+            let height = padded_height(x, z);
+
+            let mut visible_sky = 0.0;
+            for direction in &samples {
+                // Steepest slope up to the horizon in this direction
+                let mut horizon_slope: f32 = 0.0;
+                for (offset_x, offset_z, distance) in direction {
+                    let rise = padded_height(x + offset_x, z + offset_z) - height;
+                    horizon_slope = horizon_slope.max(rise / distance);
+                }
+                // The sine of the horizon angle is the share of this slice of sky it hides
+                visible_sky += 1.0 - horizon_slope / (1.0 + horizon_slope * horizon_slope).sqrt();
+            }
+            visibility.push(visible_sky / HORIZON_DIRECTIONS as f32);
+        }
+    }
+    visibility
+}
@@ -0,0 +1,220 @@
+pub mod ambient_occlusion;
+pub mod raycast;
+
+use std::sync::Arc;
+
+use crate::{
+    mesh::mesh_settings::MeshSettings,
+    noise_map::{noise_map_settings::NoiseMapSettings, NoiseMap},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    // Smooth blend of the four corners of a grid cell
+    Bilinear,
+    // Exact surface of the two triangles the mesh splits a grid cell into
+    Triangle,
+}
+
+// Which surface a height or normal query should follow
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceQuery {
+    // Same meaning as MeshSettings::level_of_detail, 0 is the full resolution surface
+    pub level_of_detail: i32,
+    pub interpolation: Interpolation,
+}
+
+impl SurfaceQuery {
+    pub fn new(level_of_detail: i32, interpolation: Interpolation) -> Self {
+        Self {
+            level_of_detail,
+            interpolation,
+        }
+    }
+
+    pub fn full_resolution() -> Self {
+        Self::new(0, Interpolation::Triangle)
+    }
+
+    // Distance between mesh vertices in grid units, matching Mesh::create_terrain_mesh
+    pub fn step(&self) -> i32 {
+        if self.level_of_detail == 0 {
+            1
+        } else {
+            self.level_of_detail * 2
+        }
+    }
+}
+
+// The heights of one chunk on its full resolution vertex grid, indexed [z * size + x]
+#[derive(Clone)]
+pub struct HeightField {
+    pub size: usize,
+    // Normalized noise heights, used for material selection
+    pub noise_heights: Vec<f32>,
+    // Heights in world units, after the mesh curve and strength. Shared so the chunks generated
+    // next to this one can read them on their own threads.
+    pub heights: Arc<Vec<f32>>,
+    // The sculpted deltas included in the heights
+    pub edits: Option<Arc<Vec<f32>>>,
+    // Sky visibility of every vertex, empty until baked
+    pub occlusion: Vec<f32>,
+}
+
+// The heights of a height field without its sculpted deltas, shared without copying
+#[derive(Clone)]
+pub struct ProceduralHeights {
+    size: usize,
+    heights: Arc<Vec<f32>>,
+    edits: Option<Arc<Vec<f32>>>,
+}
+
+impl ProceduralHeights {
+    pub fn height(&self, x: i32, z: i32) -> f32 {
+        let last = self.size as i32 - 1;
+        let index = (z.clamp(0, last) * self.size as i32 + x.clamp(0, last)) as usize;
+        self.heights[index] - self.edits.as_ref().map_or(0.0, |deltas| deltas[index])
+    }
+}
+
+impl HeightField {
+    // The terrain as the noise and mesh settings describe it, without any edits
+    pub fn generate(noise_map_settings: &NoiseMapSettings, mesh_settings: &MeshSettings) -> Self {
+        let noise_map = NoiseMap::new(*noise_map_settings);
+        let height_map = noise_map.get_height_map();
+
+        let size = noise_map_settings.width as usize;
+        let mut noise_heights = Vec::with_capacity(size * size);
+        let mut heights = Vec::with_capacity(size * size);
+
+        // The noise map is indexed [x][z], the height field row by row
+        for z in 0..size {
+            for column in height_map.iter().take(size) {
+                let noise_height = column[z] as f32;
+                noise_heights.push(noise_height);
+                heights.push(
+                    mesh_settings.curve.evaluate(noise_height as f64) as f32
+                        * mesh_settings.strength,
+                );
+            }
+        }
+
+        HeightField {
+            size,
+            noise_heights,
+            heights: Arc::new(heights),
+            edits: None,
+            occlusion: Vec::new(),
+        }
+    }
+
+    // Adds the sculpted deltas to a procedural height field
+    pub fn with_edits(
+        mut self,
+        mesh_settings: &MeshSettings,
+        edits: Option<Arc<Vec<f32>>>,
+    ) -> Self {
+        if let Some(deltas) = &edits {
+            let heights = Arc::make_mut(&mut self.heights);
+            for (index, delta) in deltas.iter().enumerate() {
+                heights[index] += delta;
+                // Move the noise height along, so material bands follow the sculpted terrain
+                if mesh_settings.strength != 0.0 {
+                    self.noise_heights[index] += delta / mesh_settings.strength;
+                }
+            }
+        }
+        self.edits = edits;
+        self
+    }
+
+    // Bakes the ambient occlusion of the heights as they are now, so apply edits first.
+    // border_height gives the heights past the edges, at grid coordinates outside the field.
+    pub fn with_occlusion(mut self, border_height: impl FnMut(i32, i32) -> f32) -> Self {
+        self.occlusion = ambient_occlusion::bake(&self, border_height);
+        self
+    }
+
+    // Fully visible sky when the occlusion has not been baked
+    pub fn occlusion(&self, x: usize, z: usize) -> f32 {
+        self.occlusion
+            .get(z * self.size + x)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn height(&self, x: i32, z: i32) -> f32 {
+        let last = self.size as i32 - 1;
+        self.heights[(z.clamp(0, last) * self.size as i32 + x.clamp(0, last)) as usize]
+    }
+
+    // Height without the sculpted deltas
+    pub fn procedural_height(&self, x: i32, z: i32) -> f32 {
+        self.procedural_heights().height(x, z)
+    }
+
+    pub fn procedural_heights(&self) -> ProceduralHeights {
+        ProceduralHeights {
+            size: self.size,
+            heights: Arc::clone(&self.heights),
+            edits: self.edits.clone(),
+        }
+    }
+
+    pub fn noise_height(&self, x: usize, z: usize) -> f32 {
+        self.noise_heights[z * self.size + x]
+    }
+}
+
+// Interpolates the height and normal at fractional grid coordinates, following the vertices
+// a mesh with the query's detail level would have. Grid z runs opposite to world z.
+pub fn surface_at(
+    mut height_at: impl FnMut(i32, i32) -> f32,
+    size: usize,
+    grid_x: f32,
+    grid_z: f32,
+    query: &SurfaceQuery,
+) -> (f32, glm::Vec3) {
+    let step = query.step();
+    let last_cell = (size as i32 - 1 - step).max(0);
+
+    let cell_x = (((grid_x / step as f32).floor() as i32) * step).clamp(0, last_cell);
+    let cell_z = (((grid_z / step as f32).floor() as i32) * step).clamp(0, last_cell);
+
+    let fx = ((grid_x - cell_x as f32) / step as f32).clamp(0.0, 1.0);
+    let fz = ((grid_z - cell_z as f32) / step as f32).clamp(0.0, 1.0);
+
+    let h00 = height_at(cell_x, cell_z);
+    let h10 = height_at(cell_x + step, cell_z);
+    let h01 = height_at(cell_x, cell_z + step);
+    let h11 = height_at(cell_x + step, cell_z + step);
+
+    // Height and its derivatives along the cell's fx and fz axes
+    let (height, d_fx, d_fz) = match query.interpolation {
+        Interpolation::Bilinear => (
+            h00 * (1.0 - fx) * (1.0 - fz)
+                + h10 * fx * (1.0 - fz)
+                + h01 * (1.0 - fx) * fz
+                + h11 * fx * fz,
+            (h10 - h00) * (1.0 - fz) + (h11 - h01) * fz,
+            (h01 - h00) * (1.0 - fx) + (h11 - h10) * fx,
+        ),
+        // The mesh splits each cell along the diagonal from (0, 0) to (1, 1)
+        Interpolation::Triangle if fx >= fz => (
+            h00 + (h10 - h00) * fx + (h11 - h10) * fz,
+            h10 - h00,
+            h11 - h10,
+        ),
+        Interpolation::Triangle => (
+            h00 + (h11 - h01) * fx + (h01 - h00) * fz,
+            h11 - h01,
+            h01 - h00,
+        ),
+    };
+
+    let d_x = d_fx / step as f32;
+    let d_z = -d_fz / step as f32;
+    let normal = glm::normalize(&glm::vec3(-d_x, 1.0, -d_z));
+
+    (height, normal)
+}
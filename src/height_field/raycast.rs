@@ -0,0 +1,83 @@
+use crate::ray::Ray;
+
+// Intersects a ray with the full resolution terrain surface. Vertices sit on integer world
+// coordinates, with their heights given by height_at(x, z), and every terrain value lies within
+// height_range. Returns the distance along the ray and the normal of the triangle that was hit.
+pub fn raycast(
+    mut height_at: impl FnMut(i32, i32) -> f32,
+    ray: &Ray,
+    max_distance: f32,
+    height_range: (f32, f32),
+) -> Option<(f32, glm::Vec3)> {
+    let (mut t_start, mut t_end) = (0.0, max_distance);
+
+    // Only the part of the ray between the lowest and highest terrain can hit anything
+    if ray.direction.y.abs() > f32::EPSILON {
+        let t_a = (height_range.0 - ray.origin.y) / ray.direction.y;
+        let t_b = (height_range.1 - ray.origin.y) / ray.direction.y;
+        t_start = f32::max(t_start, t_a.min(t_b));
+        t_end = f32::min(t_end, t_a.max(t_b));
+    } else if ray.origin.y < height_range.0 || ray.origin.y > height_range.1 {
+        return None;
+    }
+    if t_start > t_end {
+        return None;
+    }
+
+    let start = ray.at(t_start);
+    let mut cell_x = start.x.floor() as i32;
+    let mut cell_z = start.z.floor() as i32;
+
+    // DDA over the grid cells in the xz plane
+    let axis = |origin: f32, direction: f32, cell: i32| -> (i32, f32, f32) {
+        if direction > 0.0 {
+            (1, (cell as f32 + 1.0 - origin) / direction, 1.0 / direction)
+        } else if direction < 0.0 {
+            (-1, (cell as f32 - origin) / direction, -1.0 / direction)
+        } else {
+            (0, f32::INFINITY, f32::INFINITY)
+        }
+    };
+    let (step_x, mut t_next_x, t_delta_x) = axis(ray.origin.x, ray.direction.x, cell_x);
+    let (step_z, mut t_next_z, t_delta_z) = axis(ray.origin.z, ray.direction.z, cell_z);
+
+    loop {
+        // The cell's corners, with the mesh's diagonal between (x, z + 1) and (x + 1, z)
+        let mut corner = |x: i32, z: i32| glm::vec3(x as f32, height_at(x, z), z as f32);
+        let near_left = corner(cell_x, cell_z + 1);
+        let near_right = corner(cell_x + 1, cell_z + 1);
+        let far_left = corner(cell_x, cell_z);
+        let far_right = corner(cell_x + 1, cell_z);
+
+        let triangles = [
+            [near_left, far_right, far_left],
+            [far_right, near_left, near_right],
+        ];
+
+        let hit = triangles
+            .iter()
+            .filter_map(|[a, b, c]| {
+                ray.intersect_triangle(a, b, c)
+                    .map(|distance| (distance, glm::cross(&(b - a), &(c - a))))
+            })
+            .filter(|(distance, _)| *distance >= t_start && *distance <= t_end)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((distance, normal)) = hit {
+            let normal = if normal.y < 0.0 { -normal } else { normal };
+            return Some((distance, glm::normalize(&normal)));
+        }
+
+        if t_next_x.min(t_next_z) > t_end {
+            return None;
+        }
+
+        if t_next_x < t_next_z {
+            cell_x += step_x;
+            t_next_x += t_delta_x;
+        } else {
+            cell_z += step_z;
+            t_next_z += t_delta_z;
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::shader::{uniforms::UniformStruct, Shader};
+
+// A light infinitely far away, shining the same way everywhere
+#[derive(Clone)]
+pub struct DirectionalLight {
+    // Direction the light travels in, from the light towards the scene
+    pub direction: glm::Vec3,
+    pub ambient: glm::Vec3,
+    pub diffuse: glm::Vec3,
+    pub specular: glm::Vec3,
+}
+
+impl DirectionalLight {
+    pub fn new(
+        direction: glm::Vec3,
+        ambient: glm::Vec3,
+        diffuse: glm::Vec3,
+        specular: glm::Vec3,
+    ) -> DirectionalLight {
+        DirectionalLight {
+            direction: glm::normalize(&direction),
+            ambient,
+            diffuse,
+            specular,
+        }
+    }
+}
+
+impl UniformStruct for DirectionalLight {
+    fn set_uniforms(&self, shader: &Shader, name: &str) {
+        shader.set_vec3(&format!("{}.direction", name), &self.direction);
+        shader.set_vec3(&format!("{}.ambient", name), &self.ambient);
+        shader.set_vec3(&format!("{}.diffuse", name), &self.diffuse);
+        shader.set_vec3(&format!("{}.specular", name), &self.specular);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::utils;
+
+use super::light_source::LightSource;
+
+// Defined as MAX_LIGHTS in shape.frag, the sun included
+pub const MAX_LIGHTS: usize = 16;
+
+const LIGHT_BUFFER_BINDING: u32 = 2;
+
+// A light in std430 layout: five vec3s each followed by a scalar, then the quadratic
+// falloff padded to the struct's 16 byte alignment
+const FLOATS_PER_LIGHT: usize = 24;
+// The light count at the start of the buffer, padded so the array starts at 16 bytes
+const HEADER_FLOATS: usize = 4;
+
+// Every light in the scene on the GPU, as a shader storage buffer read by shape.frag
+pub struct LightBuffer {
+    ssbo_id: u32,
+}
+
+impl LightBuffer {
+    pub fn new(lights: &[LightSource]) -> Self {
+        let mut ssbo_id: u32 = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut ssbo_id);
+        }
+        let light_buffer = LightBuffer { ssbo_id };
+        light_buffer.upload(lights);
+        light_buffer
+    }
+
+    // Lights past MAX_LIGHTS are left out, the lighting panel marks them as ignored
+    pub fn upload(&self, lights: &[LightSource]) {
+        let lights = &lights[..lights.len().min(MAX_LIGHTS)];
+        let mut data: Vec<f32> = vec![0.0; HEADER_FLOATS + lights.len() * FLOATS_PER_LIGHT];
+        data[0] = f32::from_bits(lights.len() as u32);
+
+        for (index, light) in lights.iter().enumerate() {
+            let offset = HEADER_FLOATS + index * FLOATS_PER_LIGHT;
+            let values = &mut data[offset..offset + FLOATS_PER_LIGHT];
+
+            values[0..3].copy_from_slice(light.position.as_slice());
+            values[3] = f32::from_bits(light.kind.shader_index() as u32);
+            values[4..7].copy_from_slice(light.direction.as_slice());
+            values[7] = light.inner_cutoff;
+            values[8..11].copy_from_slice(light.ambient.as_slice());
+            values[11] = light.outer_cutoff;
+            values[12..15].copy_from_slice(light.diffuse.as_slice());
+            values[15] = light.attenuation.x;
+            values[16..19].copy_from_slice(light.specular.as_slice());
+            values[19] = light.attenuation.y;
+            values[20] = light.attenuation.z;
+        }
+
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.ssbo_id);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                utils::byte_size_of_array(&data),
+                utils::pointer_to_array(&data),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                LIGHT_BUFFER_BINDING,
+                self.ssbo_id,
+            );
+        }
+    }
+}
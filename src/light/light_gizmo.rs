@@ -0,0 +1,83 @@
+use crate::{
+    aabb::Aabb,
+    mesh::{gpu_mesh::GpuMesh, Mesh},
+    shader::Shader,
+    vertex_layout::{AttributeType, VertexAttribute, VertexLayout},
+};
+
+use super::light_source::{LightKind, LightSource};
+
+// Half the edge length of the cube drawn at a light
+const GIZMO_SIZE: f32 = 1.0;
+
+// Small cubes marking where the point and spot lights are
+pub struct LightGizmo {
+    pub shader: Shader,
+    mesh: GpuMesh,
+}
+
+impl LightGizmo {
+    pub fn new(shader: Shader) -> Self {
+        LightGizmo {
+            shader,
+            mesh: GpuMesh::new(&LightGizmo::create_cube()),
+        }
+    }
+
+    fn create_cube() -> Mesh {
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::new("position", 0, 3, AttributeType::Float, false),
+            VertexAttribute::new("normalVector", 1, 3, AttributeType::Float, false),
+        ]);
+
+        let mut vertex_data: Vec<u8> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut bounds = Aabb::empty();
+
+        let axes = [
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(0.0, 1.0, 0.0),
+            glm::vec3(0.0, 0.0, 1.0),
+        ];
+        // Four vertices per face, so every face gets its own normal
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                let normal: glm::Vec3 = axes[axis] * sign;
+                let u = axes[(axis + 1) % 3] * sign;
+                let v = axes[(axis + 2) % 3];
+
+                let first = (indices.len() / 6 * 4) as u32;
+                for (a, b) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                    let position = (normal + u * a + v * b) * GIZMO_SIZE;
+                    layout.push_vertex(&mut vertex_data, &[position.as_slice(), normal.as_slice()]);
+                    bounds.grow(&position);
+                }
+                indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+            }
+        }
+
+        Mesh::new(vertex_data, layout, 24, indices, bounds)
+    }
+
+    pub fn draw(&self, lights: &[LightSource], view_projection_matrix: &glm::Mat4) {
+        unsafe {
+            self.shader.activate();
+            self.mesh.bind();
+
+            for light in lights {
+                if light.kind == LightKind::Directional {
+                    continue;
+                }
+
+                let model_matrix = glm::translation(&light.position);
+                let transformation_matrix = view_projection_matrix * model_matrix;
+
+                self.shader
+                    .set_mat4("transform_matrix", &transformation_matrix);
+                self.shader.set_mat4("model_matrix", &model_matrix);
+                self.shader.set_vec3("light_color", &light.diffuse);
+                self.mesh.draw_elements();
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use imgui::{Drag, Ui};
+
+use super::light_source::{LightKind, LightSource};
+
+#[derive(Clone)]
+pub struct LightSettings {
+    pub name: String,
+    pub kind: LightKind,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    // Constant, linear and quadratic falloff
+    pub attenuation: [f32; 3],
+    // Spot cone angles in degrees
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+impl LightSettings {
+    // A warm light reaching about a hundred units, like a campfire
+    pub fn point(name: String, position: glm::Vec3) -> LightSettings {
+        LightSettings {
+            name,
+            kind: LightKind::Point,
+            position: position.into(),
+            direction: [0.0, -1.0, 0.0],
+            ambient: [0.1, 0.05, 0.02],
+            diffuse: [1.0, 0.6, 0.25],
+            specular: [0.5, 0.35, 0.2],
+            attenuation: [1.0, 0.045, 0.0075],
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+        }
+    }
+
+    pub fn spot(name: String, position: glm::Vec3, direction: glm::Vec3) -> LightSettings {
+        LightSettings {
+            kind: LightKind::Spot,
+            direction: direction.into(),
+            ambient: [0.0, 0.0, 0.0],
+            diffuse: [1.0, 0.95, 0.8],
+            specular: [0.6, 0.6, 0.5],
+            ..LightSettings::point(name, position)
+        }
+    }
+
+    pub fn directional(name: String, direction: glm::Vec3) -> LightSettings {
+        LightSettings {
+            kind: LightKind::Directional,
+            direction: direction.into(),
+            ambient: [0.05, 0.05, 0.05],
+            diffuse: [0.3, 0.3, 0.35],
+            specular: [0.1, 0.1, 0.1],
+            ..LightSettings::point(name, glm::vec3(0.0, 0.0, 0.0))
+        }
+    }
+
+    pub fn get_light_source(&self) -> LightSource {
+        let direction = glm::Vec3::from(self.direction);
+        let direction = if glm::length(&direction) > 0.0 {
+            glm::normalize(&direction)
+        } else {
+            glm::vec3(0.0, -1.0, 0.0)
+        };
+        // Keep the cone edges apart, the shader blends between them
+        let outer_angle = self.outer_angle.max(self.inner_angle + 0.1);
+
+        LightSource {
+            kind: self.kind,
+            position: self.position.into(),
+            direction,
+            ambient: self.ambient.into(),
+            diffuse: self.diffuse.into(),
+            specular: self.specular.into(),
+            attenuation: self.attenuation.into(),
+            inner_cutoff: self.inner_angle.to_radians().cos(),
+            outer_cutoff: outer_angle.to_radians().cos(),
+        }
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        ui.input_text("Name", &mut self.name).build();
+        ui.radio_button("Point", &mut self.kind, LightKind::Point);
+        ui.same_line();
+        ui.radio_button("Spot", &mut self.kind, LightKind::Spot);
+        ui.same_line();
+        ui.radio_button("Directional", &mut self.kind, LightKind::Directional);
+
+        if self.kind != LightKind::Directional {
+            Drag::new("Position")
+                .speed(0.5)
+                .build_array(ui, &mut self.position);
+        }
+        if self.kind != LightKind::Point {
+            Drag::new("Direction")
+                .speed(0.01)
+                .range(-1.0, 1.0)
+                .build_array(ui, &mut self.direction);
+        }
+
+        ui.color_edit3("Ambient", &mut self.ambient);
+        ui.color_edit3("Diffuse", &mut self.diffuse);
+        ui.color_edit3("Specular", &mut self.specular);
+
+        if self.kind != LightKind::Directional {
+            ui.slider("Constant", 0.0, 2.0, &mut self.attenuation[0]);
+            ui.slider("Linear", 0.0, 0.5, &mut self.attenuation[1]);
+            ui.slider("Quadratic", 0.0, 0.1, &mut self.attenuation[2]);
+        }
+        if self.kind == LightKind::Spot {
+            ui.slider("Inner angle", 0.0, 90.0, &mut self.inner_angle);
+            ui.slider("Outer angle", 0.0, 90.0, &mut self.outer_angle);
+        }
+    }
+}
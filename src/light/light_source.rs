@@ -0,0 +1,54 @@
+use super::directional_light::DirectionalLight;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Point,
+    Spot,
+    Directional,
+}
+
+impl LightKind {
+    // The LIGHT_* defines of shape.frag
+    pub fn shader_index(self) -> i32 {
+        match self {
+            LightKind::Point => 0,
+            LightKind::Spot => 1,
+            LightKind::Directional => 2,
+        }
+    }
+}
+
+// One light as the shaders see it
+#[derive(Clone)]
+pub struct LightSource {
+    pub kind: LightKind,
+    pub position: glm::Vec3,
+    // Direction the light travels in, used by spot and directional lights
+    pub direction: glm::Vec3,
+    pub ambient: glm::Vec3,
+    pub diffuse: glm::Vec3,
+    pub specular: glm::Vec3,
+    // Constant, linear and quadratic terms of the falloff over distance, unused by
+    // directional lights
+    pub attenuation: glm::Vec3,
+    // Cosines of the angles from the spot direction where the light starts to fade and
+    // where it is gone
+    pub inner_cutoff: f32,
+    pub outer_cutoff: f32,
+}
+
+impl From<&DirectionalLight> for LightSource {
+    fn from(light: &DirectionalLight) -> Self {
+        LightSource {
+            kind: LightKind::Directional,
+            position: glm::vec3(0.0, 0.0, 0.0),
+            direction: light.direction,
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            attenuation: glm::vec3(1.0, 0.0, 0.0),
+            inner_cutoff: -1.0,
+            outer_cutoff: -1.0,
+        }
+    }
+}
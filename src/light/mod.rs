@@ -1,2 +1,7 @@
-pub mod point_light;
-pub mod point_light_settings;
+pub mod directional_light;
+pub mod light_buffer;
+pub mod light_gizmo;
+pub mod light_settings;
+pub mod light_source;
+pub mod scene_lights;
+pub mod time_of_day;
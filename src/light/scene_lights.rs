@@ -0,0 +1,77 @@
+use imgui::{CollapsingHeader, Ui};
+
+use super::{light_buffer::MAX_LIGHTS, light_settings::LightSettings, light_source::LightSource};
+
+// The lights placed in the scene, besides the sun
+#[derive(Clone)]
+pub struct SceneLights {
+    pub lights: Vec<LightSettings>,
+}
+
+impl SceneLights {
+    pub fn new(lights: Vec<LightSettings>) -> SceneLights {
+        SceneLights { lights }
+    }
+
+    pub fn get_light_sources(&self) -> Vec<LightSource> {
+        self.lights
+            .iter()
+            .map(|light| light.get_light_source())
+            .collect()
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        let mut removed: Option<usize> = None;
+
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            let _id = ui.push_id_usize(index);
+
+            let mut keep = true;
+            if CollapsingHeader::new(&light.name).build_with_close_button(ui, &mut keep) {
+                // The sun takes up one of the lights the shader can read
+                if index + 1 >= MAX_LIGHTS {
+                    ui.text_disabled(format!(
+                        "Ignored, the shader reads at most {} lights besides the sun",
+                        MAX_LIGHTS - 1
+                    ));
+                }
+                light.render(ui);
+            }
+            if !keep {
+                removed = Some(index);
+            }
+        }
+
+        if let Some(index) = removed {
+            self.lights.remove(index);
+        }
+
+        let at_limit = self.lights.len() + 1 >= MAX_LIGHTS;
+        if at_limit {
+            ui.text(format!("At most {} lights besides the sun", MAX_LIGHTS - 1));
+        }
+        let _disabled = ui.begin_disabled(at_limit);
+
+        let new_light_name = format!("Light {}", self.lights.len() + 1);
+        if ui.button("Add point light") {
+            self.lights.push(LightSettings::point(
+                new_light_name.clone(),
+                glm::vec3(0.0, 10.0, 0.0),
+            ));
+        }
+        ui.same_line();
+        if ui.button("Add spot light") {
+            self.lights.push(LightSettings::spot(
+                new_light_name.clone(),
+                glm::vec3(0.0, 30.0, 0.0),
+                glm::vec3(0.0, -1.0, 0.0),
+            ));
+        }
+        if ui.button("Add directional light") {
+            self.lights.push(LightSettings::directional(
+                new_light_name,
+                glm::vec3(0.3, -1.0, 0.2),
+            ));
+        }
+    }
+}
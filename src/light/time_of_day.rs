@@ -0,0 +1,161 @@
+use imgui::{CollapsingHeader, Ui};
+
+use super::directional_light::DirectionalLight;
+
+const HOURS_PER_DAY: f32 = 24.0;
+const SUNRISE_HOUR: f32 = 6.0;
+
+// Tilts the sun's path away from straight overhead, so noon still casts some shade
+const SUN_PATH_TILT: f32 = 0.35;
+
+// Sun, ambient and sky colour at a time of day
+struct SkyKey {
+    hour: f32,
+    sun: [f32; 3],
+    ambient: [f32; 3],
+    sky: [f32; 3],
+}
+
+const NIGHT_SUN: [f32; 3] = [0.0, 0.0, 0.0];
+const NIGHT_AMBIENT: [f32; 3] = [0.06, 0.07, 0.11];
+const NIGHT_SKY: [f32; 3] = [0.035, 0.046, 0.078];
+
+// Ordered by hour, the first and last keys are equal so the day wraps around
+const SKY_KEYS: [SkyKey; 9] = [
+    SkyKey {
+        hour: 0.0,
+        sun: NIGHT_SUN,
+        ambient: NIGHT_AMBIENT,
+        sky: NIGHT_SKY,
+    },
+    SkyKey {
+        hour: 5.0,
+        sun: NIGHT_SUN,
+        ambient: NIGHT_AMBIENT,
+        sky: NIGHT_SKY,
+    },
+    // Dawn
+    SkyKey {
+        hour: 6.5,
+        sun: [1.0, 0.5, 0.25],
+        ambient: [0.3, 0.22, 0.22],
+        sky: [0.85, 0.5, 0.35],
+    },
+    SkyKey {
+        hour: 8.5,
+        sun: [1.0, 0.88, 0.72],
+        ambient: [0.38, 0.38, 0.42],
+        sky: [0.5, 0.7, 0.92],
+    },
+    // Noon
+    SkyKey {
+        hour: 12.0,
+        sun: [1.0, 0.98, 0.92],
+        ambient: [0.42, 0.42, 0.46],
+        sky: [0.45, 0.7, 1.0],
+    },
+    SkyKey {
+        hour: 15.5,
+        sun: [1.0, 0.88, 0.72],
+        ambient: [0.38, 0.38, 0.42],
+        sky: [0.5, 0.7, 0.92],
+    },
+    // Dusk
+    SkyKey {
+        hour: 17.5,
+        sun: [1.0, 0.42, 0.18],
+        ambient: [0.3, 0.2, 0.22],
+        sky: [0.85, 0.4, 0.28],
+    },
+    SkyKey {
+        hour: 19.0,
+        sun: NIGHT_SUN,
+        ambient: NIGHT_AMBIENT,
+        sky: NIGHT_SKY,
+    },
+    SkyKey {
+        hour: HOURS_PER_DAY,
+        sun: NIGHT_SUN,
+        ambient: NIGHT_AMBIENT,
+        sky: NIGHT_SKY,
+    },
+];
+
+// Drives the sun and sky colours through a day
+#[derive(Clone)]
+pub struct TimeOfDay {
+    pub name: String,
+    // Hours since midnight
+    pub hour: f32,
+    // Hours that pass per second
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl TimeOfDay {
+    pub fn new(name: String, hour: f32) -> TimeOfDay {
+        TimeOfDay {
+            name,
+            hour,
+            speed: 0.1,
+            paused: false,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if !self.paused {
+            self.hour = (self.hour + self.speed * delta_time).rem_euclid(HOURS_PER_DAY);
+        }
+    }
+
+    // Unit vector from the scene towards the sun. The sun rises in the east (+x) at
+    // SUNRISE_HOUR, is highest at noon and sets in the west.
+    pub fn sun_position(&self) -> glm::Vec3 {
+        let angle = (self.hour - SUNRISE_HOUR) / HOURS_PER_DAY * std::f32::consts::TAU;
+        glm::normalize(&glm::vec3(angle.cos(), angle.sin(), SUN_PATH_TILT))
+    }
+
+    pub fn get_sun_light(&self) -> DirectionalLight {
+        let (sun, ambient, _) = self.colors();
+        let sun = glm::Vec3::from(sun);
+        DirectionalLight::new(-self.sun_position(), ambient.into(), sun, sun * 0.5)
+    }
+
+    pub fn sky_color(&self) -> glm::Vec3 {
+        self.colors().2.into()
+    }
+
+    // Sun, ambient and sky colour, blended between the surrounding keys
+    fn colors(&self) -> ([f32; 3], [f32; 3], [f32; 3]) {
+        let hour = self.hour.rem_euclid(HOURS_PER_DAY);
+        let next = SKY_KEYS
+            .iter()
+            .position(|key| key.hour > hour)
+            .unwrap_or(SKY_KEYS.len() - 1);
+        let (from, to) = (&SKY_KEYS[next - 1], &SKY_KEYS[next]);
+        let t = ((hour - from.hour) / (to.hour - from.hour)).clamp(0.0, 1.0);
+
+        let mix = |a: [f32; 3], b: [f32; 3]| {
+            [
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ]
+        };
+        (
+            mix(from.sun, to.sun),
+            mix(from.ambient, to.ambient),
+            mix(from.sky, to.sky),
+        )
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            let minutes = (self.hour.fract() * 60.0) as i32;
+            ui.text(format!("Time: {:02}:{:02}", self.hour as i32, minutes));
+            ui.slider("Hour", 0.0, HOURS_PER_DAY, &mut self.hour);
+            ui.slider("Hours per second", 0.0, 2.0, &mut self.speed);
+            ui.checkbox("Pause", &mut self.paused);
+        }
+    }
+}
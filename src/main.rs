@@ -1,40 +1,63 @@
 extern crate nalgebra_glm as glm;
-use std::{cmp::Ordering, ptr};
-
 use camera::Camera;
-use chunk::ChunkContainer;
+use capture::capture_options::{CaptureOptions, USAGE};
+use chunk::{region_cache::RegionCache, streaming_settings::StreamingSettings, ChunkContainer};
 use curve_editor::curve::Curve;
+use frame_time_graph::FrameTimeGraph;
 use glutin::event::{
     ElementState::{Pressed, Released},
-    Event, KeyboardInput,
+    Event, KeyboardInput, MouseButton,
     VirtualKeyCode::{self, *},
     WindowEvent,
 };
 use glutin::event_loop::ControlFlow;
+use height_field::SurfaceQuery;
 
 pub mod light;
-use light::point_light::PointLight;
-use light::point_light_settings::PointLightSettings;
 pub mod scenenode;
-use imgui::Condition;
-use material::{material_settings::MaterialSettings, Material};
+use imgui::{Condition, StyleColor};
+use material::palette::{Palette, PaletteFile};
 use mesh::mesh_settings::MeshSettings;
 use noise_map::noise_map_settings;
-use scenenode::SceneNode;
+use ray::Ray;
+use renderer::{Output, Renderer, SceneSettings};
+use sculpt::{brush::Brush, terrain_edits::TerrainEdits};
+use shader::shader_watcher::ShaderWatcher;
+pub mod aabb;
 pub mod camera;
+pub mod capture;
 pub mod chunk;
 pub mod curve_editor;
+pub mod debug_view;
+pub mod fog;
+pub mod frame_time_graph;
+pub mod gradient_editor;
+pub mod height_field;
 pub mod lod;
 pub mod material;
 pub mod mesh;
 pub mod noise_map;
+pub mod ray;
+pub mod render_target;
+pub mod renderer;
+pub mod sculpt;
 pub mod shader;
+pub mod shadow;
+pub mod sky;
 pub mod triangle;
 pub mod utils;
 pub mod vertex;
+pub mod vertex_layout;
+pub mod water;
 
 const CHUNK_PIXEL_SIZE: i32 = 480;
-const WATER_LEVEL: f64 = 0.0;
+// Initial height of the water surface, just under the sand band of the default palette
+const WATER_LEVEL: f32 = 4.0;
+
+const PALETTE_PATH: &str = "./palettes/default.toml";
+const EDITS_PATH: &str = "./edits/terrain.edits";
+const CHUNK_CACHE_DIRECTORY: &str = "./cache/chunks";
+const SHADER_DIRECTORY: &str = "./shaders";
 
 // initial window size
 const INITIAL_SCREEN_W: u32 = 800;
@@ -42,56 +65,27 @@ const INITIAL_SCREEN_H: u32 = 600;
 
 const VIEW_DISTANCE: f32 = 600.0;
 
-unsafe fn draw_scene(
-    nodes: &Vec<scenenode::SceneNode>,
-    view_projection_matrix: &glm::Mat4,
-    light: &PointLight,
-    cam_pos: &glm::Vec3,
-) {
-    for node in nodes {
-        if node.vao_id == 0 {
-            continue;
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    match CaptureOptions::parse(&args) {
+        Ok(Some(options)) => {
+            if let Err(error) = capture::run(&options) {
+                println!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(error) => {
+            println!("{}\n\n{}", error, USAGE);
+            std::process::exit(1);
         }
-        let mut model_matrix = glm::Mat4::identity();
-        model_matrix = glm::translation(&glm::vec3(
-            node.reference_point.x * -1.0,
-            node.reference_point.y * -1.0,
-            node.reference_point.z * -1.0,
-        )) * model_matrix;
-
-        model_matrix = glm::rotation(node.rotation.x, &glm::vec3(1.0, 0.0, 0.0)) * model_matrix;
-        model_matrix = glm::rotation(node.rotation.y, &glm::vec3(0.0, 1.0, 0.0)) * model_matrix;
-        model_matrix = glm::rotation(node.rotation.z, &glm::vec3(0.0, 0.0, 1.0)) * model_matrix;
-
-        model_matrix = glm::scale(&model_matrix, &node.scale);
-
-        model_matrix = glm::translation(&node.reference_point) * model_matrix;
-        model_matrix = glm::translation(&node.position) * model_matrix;
-
-        let transformation_matrix: glm::Mat4 = view_projection_matrix * model_matrix;
-
-        gl::UseProgram(node.shader_program);
-        gl::BindVertexArray(node.vao_id);
-
-        gl::UniformMatrix4fv(10, 1, gl::TRUE, transformation_matrix.as_ptr());
-        gl::UniformMatrix4fv(11, 1, gl::TRUE, model_matrix.as_ptr());
-        gl::Uniform3fv(12, 1, cam_pos.as_ptr());
-
-        gl::Uniform3fv(13, 1, light.position.as_ptr());
-        gl::Uniform3fv(14, 1, light.ambient.as_ptr());
-        gl::Uniform3fv(15, 1, light.diffuse.as_ptr());
-        gl::Uniform3fv(16, 1, light.specular.as_ptr());
-
-        gl::DrawElements(
-            gl::TRIANGLES,
-            node.index_count,
-            gl::UNSIGNED_INT,
-            ptr::null(),
-        );
     }
-}
 
-fn main() {
     let event_loop = glutin::event_loop::EventLoop::new();
     let window_builder = glutin::window::WindowBuilder::new()
         .with_title("terrain-generator")
@@ -126,68 +120,60 @@ fn main() {
         .fonts()
         .add_font(&[imgui::FontSource::DefaultFontData { config: None }]);
 
-    let renderer =
+    let imgui_renderer =
         imgui_opengl_renderer::Renderer::new(&mut imgui, |s| context.get_proc_address(s) as _);
 
     let mut pressed_keys = Vec::<VirtualKeyCode>::with_capacity(10);
     let mut window_size = (INITIAL_SCREEN_W, INITIAL_SCREEN_H, false);
+    let mut cursor_position: (f32, f32) = (0.0, 0.0);
+    let mut left_mouse_pressed = false;
+    let mut screenshot_requested = false;
     let mut window_aspect_ratio = INITIAL_SCREEN_W as f32 / INITIAL_SCREEN_H as f32;
 
-    // Set up openGL
-    unsafe {
-        gl::Enable(gl::DEPTH_TEST);
-        gl::DepthFunc(gl::LESS);
-        gl::Enable(gl::CULL_FACE);
-        gl::Disable(gl::MULTISAMPLE);
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
-    }
-
-    let shape_shader = unsafe {
-        shader::ShaderBuilder::new()
-            .attach_file("./shaders/shape.vert")
-            .attach_file("./shaders/shape.frag")
-            .link()
-    };
-
     let cubic_curve = Curve::quadratic();
     let mut mesh_settings = MeshSettings::new(" Mesh".to_string(), 10.0, cubic_curve, 0);
     let mut noise_map_settings = noise_map_settings::NoiseMapSettings::new();
 
-    let mut point_light_settings = PointLightSettings {
-        name: "Point Light".to_string(),
-        position: glm::vec3(0.0, 10.0, 0.0),
-        ambient: glm::vec3(0.7, 0.7, 0.7),
-        diffuse: glm::vec3(0.2, 0.2, 0.2),
-        specular: glm::vec3(0.4, 0.4, 0.4),
-    };
+    let mut scene_settings = SceneSettings::new(10.0, WATER_LEVEL);
 
-    let water_material_settings = MaterialSettings::standard_water_material();
-    let sand_material_settings = MaterialSettings::standard_sand_material();
-    let grass_material_settings = MaterialSettings::standard_grass_material();
-    let snow_material_settings = MaterialSettings::standard_snow_material();
+    let mut palette_file = PaletteFile::new(PALETTE_PATH);
+    let mut palette = palette_file.load().unwrap_or_else(|e| {
+        println!("Using the standard palette. {}", e);
+        Palette::standard()
+    });
+    let mut edited_palette = palette.clone();
+    let mut palette_error: Option<String> = None;
 
-    let grass_material = Material::new(&grass_material_settings);
-    let sand_material = Material::new(&sand_material_settings);
-    let water_material = Material::new(&water_material_settings);
-    let snow_material = Material::new(&snow_material_settings);
+    let mut coloring = palette.build_coloring();
 
-    let mut materials = vec![water_material, sand_material, grass_material, snow_material];
-    let mut material_settings = vec![
-        water_material_settings,
-        sand_material_settings,
-        grass_material_settings,
-        snow_material_settings,
-    ];
+    let mut renderer = Renderer::new(&coloring, &scene_settings).unwrap_or_else(|error| {
+        println!("{}", error);
+        std::process::exit(1);
+    });
+    let mut shader_watcher = ShaderWatcher::new(SHADER_DIRECTORY);
+
+    let edits = TerrainEdits::load(EDITS_PATH).unwrap_or_else(|e| {
+        println!("Starting without terrain edits. {}", e);
+        TerrainEdits::default()
+    });
+    let mut edits_error: Option<String> = None;
+
+    let mut brush = Brush::default();
+    let mut edit_mode = false;
+    let mut stroke_in_progress = false;
 
     let mut chunk_container = ChunkContainer::new(
         CHUNK_PIXEL_SIZE + 1,
         VIEW_DISTANCE,
-        &materials,
+        &coloring,
         &mut noise_map_settings,
         &mesh_settings,
+        edits,
+        RegionCache::new(CHUNK_CACHE_DIRECTORY),
     );
+    let mut cache_message: Option<String> = None;
+    let mut streaming_settings = StreamingSettings::new("Streaming".to_string());
+    let mut frame_time_graph = FrameTimeGraph::default();
 
     let first_frame_time = std::time::Instant::now();
     let mut previous_frame_time = first_frame_time;
@@ -248,6 +234,9 @@ fn main() {
                     Q => {
                         *control_flow = ControlFlow::Exit;
                     }
+                    F12 if key_state == Pressed => {
+                        screenshot_requested = true;
+                    }
                     _ => {}
                 }
             }
@@ -264,9 +253,11 @@ fn main() {
             Event::RedrawRequested(_) => {
                 // Compute time passed since the previous frame and since the start of the program
                 let now = std::time::Instant::now();
-                let _elapsed = now.duration_since(first_frame_time).as_secs_f32();
+                let elapsed = now.duration_since(first_frame_time).as_secs_f32();
                 let delta_time = now.duration_since(previous_frame_time).as_secs_f32();
                 previous_frame_time = now;
+                // The uploads of the last update happened during the frame just measured
+                frame_time_graph.push(delta_time, chunk_container.bytes_uploaded);
 
                 imgui
                     .io_mut()
@@ -290,83 +281,192 @@ fn main() {
                     }
                 }
 
+                scene_settings.time_of_day.update(delta_time);
+
                 // Handle keyboard input
                 for key in pressed_keys.iter() {
                     camera.handle_key_input(*key, delta_time);
                 }
 
-                unsafe {
-                    let mut transformation_matrix: glm::Mat4 = glm::identity();
-                    let mut view_matrix: glm::Mat4 = glm::identity();
+                let changed_shaders = shader_watcher.poll();
+                if !changed_shaders.is_empty() {
+                    renderer.reload_shaders(&changed_shaders);
+                }
 
+                unsafe {
                     camera.update_camera_vectors();
-                    view_matrix = camera.get_look_at_matrix() * view_matrix;
-
-                    let projection_matrix: glm::Mat4 =
-                        glm::perspective(window_aspect_ratio, glm::half_pi(), 1.0, VIEW_DISTANCE);
-
-                    transformation_matrix = projection_matrix * view_matrix * transformation_matrix;
-
-                    // Clear the color and depth buffers
-                    gl::ClearColor(0.035, 0.046, 0.078, 1.0); // night sky, full opacity
-                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                    let transformation_matrix = Renderer::projection_matrix(window_aspect_ratio)
+                        * camera.get_look_at_matrix();
 
                     let window = context.window();
                     let ui = imgui.frame();
 
                     let mut new_mesh_settings = mesh_settings.clone();
                     let mut new_noise_map_settings = noise_map_settings.clone();
-                    let mut new_material_settings = material_settings.clone();
+
+                    // Pick the terrain under the mouse, unless it is over an imgui window
+                    let hovered_terrain = if ui.io().want_capture_mouse {
+                        None
+                    } else {
+                        let ray = Ray::from_screen(
+                            cursor_position,
+                            (window_size.0 as f32, window_size.1 as f32),
+                            &transformation_matrix,
+                        );
+                        chunk_container.raycast(&ray, VIEW_DISTANCE)
+                    };
 
                     ui.window("Settings")
                         .size([300.0, 800.0], Condition::FirstUseEver)
                         .build(|| {
                             ui.text(format!("FPS: {}", (1.0 / delta_time).ceil()));
+                            frame_time_graph.render(ui);
+                            let (acmr_before, acmr_after) = chunk_container.acmr_stats();
+                            ui.text(format!("ACMR: {:.3} -> {:.3}", acmr_before, acmr_after));
+                            ui.text(format!(
+                                "Chunks drawn: {}, culled: {}",
+                                chunk_container.chunks_drawn, chunk_container.chunks_culled
+                            ));
+                            let (pool_used, pool_allocated) = chunk_container.mesh_pool().usage();
+                            ui.text(format!(
+                                "Mesh pool: {:.1} / {:.1} MB",
+                                pool_used as f32 / (1024.0 * 1024.0),
+                                pool_allocated as f32 / (1024.0 * 1024.0)
+                            ));
+                            let ground_height = chunk_container.height_at(
+                                camera.position.x,
+                                camera.position.z,
+                                &SurfaceQuery::full_resolution(),
+                            );
+                            ui.text(format!("Ground height: {:.2}", ground_height));
+                            let (cache_hits, cache_misses) = chunk_container.cache_stats();
+                            ui.text(format!(
+                                "Chunk cache: {} loaded, {} generated",
+                                cache_hits, cache_misses
+                            ));
+                            if ui.button("Prune chunk cache") {
+                                cache_message = Some(match chunk_container.prune_cache() {
+                                    Ok(dropped) => format!("Dropped {} stale chunks", dropped),
+                                    Err(error) => error,
+                                });
+                            }
+                            if let Some(message) = &cache_message {
+                                ui.text(message);
+                            }
+                            match &hovered_terrain {
+                                Some(hit) => {
+                                    ui.text(format!(
+                                        "Hovered: ({:.1}, {:.1}), height: {:.2}",
+                                        hit.position.x, hit.position.z, hit.position.y
+                                    ));
+                                    let material_name = hit
+                                        .material
+                                        .and_then(|index| palette.materials.get(index))
+                                        .map_or("-", |material| material.name.as_str());
+                                    ui.text(format!(
+                                        "Hovered chunk: ({}, {}), material: {}",
+                                        hit.chunk.0, hit.chunk.1, material_name
+                                    ));
+                                }
+                                None => ui.text("Hovered: -"),
+                            }
                             ui.separator();
+                            scene_settings.debug.render(ui);
+                            if ui.button("Print shader interfaces") {
+                                println!("{}", renderer.describe_shaders());
+                            }
 
+                            ui.separator();
                             ui.text("Terrain Settings");
                             new_noise_map_settings.render(ui);
 
                             ui.text("Mesh Settings");
                             new_mesh_settings.render(ui);
+                            streaming_settings.render(ui);
 
                             ui.separator();
                             ui.text("Lighting");
-                            point_light_settings.render(ui);
+                            scene_settings.time_of_day.render(ui);
+                            scene_settings.sky.render(ui);
+                            scene_settings.fog.render(ui);
+                            scene_settings.lights.render(ui);
+                            scene_settings.shadows.render(ui);
+
+                            ui.separator();
+                            scene_settings.water.render(ui);
 
                             ui.separator();
                             ui.text("Material Settings");
-                            for material_setting in &mut new_material_settings {
-                                material_setting.render(ui);
+                            edited_palette.render(ui);
+                            if ui.button("Save palette") {
+                                palette_error = palette_file.save(&edited_palette).err();
+                            }
+                            if let Some(error) = &palette_error {
+                                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+                            }
+
+                            ui.separator();
+                            ui.text("Sculpting");
+                            ui.checkbox("Edit mode", &mut edit_mode);
+                            brush.render(ui);
+                            if ui.button("Save edits") {
+                                edits_error = chunk_container.edits().save(EDITS_PATH).err();
+                            }
+                            ui.same_line();
+                            if ui.button("Clear edits") {
+                                chunk_container.clear_edits();
+                            }
+                            if let Some(error) = &edits_error {
+                                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
                             }
                         });
 
-                    let mut should_rebuild = false;
+                    let shader_errors = renderer.shader_errors();
+                    if !shader_errors.is_empty() {
+                        ui.window("Shader errors")
+                            .size([500.0, 300.0], Condition::FirstUseEver)
+                            .build(|| {
+                                ui.text("The last working version is used until these are fixed");
+                                for error in &shader_errors {
+                                    ui.separator();
+                                    let _color =
+                                        ui.push_style_color(StyleColor::Text, [1.0, 0.4, 0.4, 1.0]);
+                                    ui.text_wrapped(error);
+                                }
+                            });
+                    }
 
-                    if new_material_settings != material_settings {
-                        // Update materials to match new settings
-                        let mut new_materials = vec![];
-                        for material_setting in &new_material_settings {
-                            new_materials.push(Material::new(&MaterialSettings {
-                                name: material_setting.name.clone(),
-                                ambient: material_setting.ambient,
-                                diffuse: material_setting.diffuse,
-                                specular: material_setting.specular,
-                                shininess: material_setting.shininess,
-                                height_limit: material_setting.height_limit,
-                            }));
+                    // Drag with the left mouse button to sculpt in edit mode
+                    match &hovered_terrain {
+                        Some(hit) if edit_mode && left_mouse_pressed => {
+                            if !stroke_in_progress {
+                                brush.begin_stroke(hit.position.y);
+                                stroke_in_progress = true;
+                            }
+                            chunk_container.sculpt(&brush, &hit.position, delta_time);
                         }
+                        _ => stroke_in_progress &= left_mouse_pressed,
+                    }
 
-                        //Sort materials
-                        new_materials.sort_by(|a, b| {
-                            a.height_limit
-                                .partial_cmp(&b.height_limit)
-                                .unwrap_or(Ordering::Equal)
-                        });
+                    let mut should_rebuild = false;
 
-                        material_settings = new_material_settings;
-                        materials = new_materials;
-                        should_rebuild = true;
+                    match palette_file.poll() {
+                        Some(Ok(reloaded_palette)) => edited_palette = reloaded_palette,
+                        Some(Err(error)) => palette_error = Some(error),
+                        None => {}
+                    }
+
+                    if edited_palette != palette {
+                        match edited_palette.validate() {
+                            Ok(()) => {
+                                should_rebuild |= edited_palette.requires_rebuild(&palette);
+                                palette = edited_palette.clone();
+                                coloring = palette.build_coloring();
+                                renderer.material_buffer.upload(&coloring);
+                                palette_error = None;
+                            }
+                            Err(error) => palette_error = Some(error),
+                        }
                     }
 
                     if new_noise_map_settings != noise_map_settings {
@@ -382,7 +482,7 @@ fn main() {
 
                     if should_rebuild {
                         chunk_container.update_settings(
-                            &materials,
+                            &coloring,
                             &noise_map_settings,
                             &mesh_settings,
                         );
@@ -391,19 +491,34 @@ fn main() {
 
                     chunk_container.generate_visible_chunks(camera.position);
 
-                    chunk_container.update_chunk_map();
-
-                    let scene: Vec<SceneNode> =
-                        chunk_container.generate_scene(shape_shader.program_id, camera.position);
+                    chunk_container.update_chunk_map(streaming_settings.upload_budget());
+
+                    let output = Output::Window(window_size.0 as i32, window_size.1 as i32);
+                    if screenshot_requested {
+                        screenshot_requested = false;
+                        let pixels = renderer.render_to_image(
+                            &mut chunk_container,
+                            &camera,
+                            &scene_settings,
+                            elapsed,
+                            output.size(),
+                        );
+                        let path = capture::screenshot_path();
+                        match capture::save_png(&path, output.size(), &pixels) {
+                            Ok(()) => println!("Saved {}", path),
+                            Err(error) => println!("{}", error),
+                        }
+                    }
 
-                    draw_scene(
-                        &scene,
-                        &transformation_matrix,
-                        &point_light_settings.get_point_light(),
-                        &camera.position,
+                    renderer.render(
+                        &mut chunk_container,
+                        &camera,
+                        &scene_settings,
+                        elapsed,
+                        output,
                     );
                     winit_platform.prepare_render(&ui, &window);
-                    renderer.render(&mut imgui);
+                    imgui_renderer.render(&mut imgui);
                 }
 
                 // Display the new color buffer on the display
@@ -414,6 +529,25 @@ fn main() {
                 ..
             } => *control_flow = ControlFlow::Exit,
             event => {
+                if let Event::WindowEvent {
+                    event: WindowEvent::CursorMoved { position, .. },
+                    ..
+                } = event
+                {
+                    cursor_position = (position.x as f32, position.y as f32);
+                }
+                if let Event::WindowEvent {
+                    event:
+                        WindowEvent::MouseInput {
+                            state,
+                            button: MouseButton::Left,
+                            ..
+                        },
+                    ..
+                } = event
+                {
+                    left_mouse_pressed = state == Pressed;
+                }
                 winit_platform.handle_event(imgui.io_mut(), context.window(), &event);
             }
         }
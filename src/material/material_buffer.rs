@@ -0,0 +1,114 @@
+use std::ptr;
+
+use crate::utils;
+
+use super::TerrainColoring;
+
+// Defined as MAX_MATERIALS in shape.frag
+pub const MAX_MATERIALS: usize = 32;
+
+const MATERIAL_BUFFER_BINDING: u32 = 0;
+const GRADIENT_TEXTURE_UNIT: u32 = 1;
+const GRADIENT_RESOLUTION: usize = 256;
+
+// Three vec4s per material in std140 layout: ambient, diffuse and specular with shininess in w
+const FLOATS_PER_MATERIAL: usize = 12;
+
+// The material palette on the GPU, as a uniform buffer plus a 1D texture for the height gradient
+pub struct MaterialBuffer {
+    ubo_id: u32,
+    gradient_texture_id: u32,
+}
+
+impl MaterialBuffer {
+    pub fn new(coloring: &TerrainColoring) -> Self {
+        unsafe {
+            let mut ubo_id: u32 = 0;
+            gl::GenBuffers(1, &mut ubo_id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, ubo_id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                ((MAX_MATERIALS * FLOATS_PER_MATERIAL + 4) * std::mem::size_of::<f32>()) as isize,
+                ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+
+            let mut gradient_texture_id: u32 = 0;
+            gl::GenTextures(1, &mut gradient_texture_id);
+            gl::BindTexture(gl::TEXTURE_1D, gradient_texture_id);
+            gl::TexParameteri(gl::TEXTURE_1D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_1D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_1D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_1D, 0);
+
+            let material_buffer = MaterialBuffer {
+                ubo_id,
+                gradient_texture_id,
+            };
+            material_buffer.upload(coloring);
+            material_buffer
+        }
+    }
+
+    pub fn upload(&self, coloring: &TerrainColoring) {
+        unsafe {
+            let mut data: Vec<f32> = vec![0.0; MAX_MATERIALS * FLOATS_PER_MATERIAL + 4];
+
+            for (index, material) in coloring.materials.iter().take(MAX_MATERIALS).enumerate() {
+                let offset = index * FLOATS_PER_MATERIAL;
+                data[offset..offset + 3].copy_from_slice(&material.ambient);
+                data[offset + 4..offset + 7].copy_from_slice(&material.diffuse);
+                data[offset + 8..offset + 11].copy_from_slice(&material.specular);
+                data[offset + 11] = material.shininess;
+            }
+
+            // Trailing vec4 with palette settings, x toggles the height gradient
+            let settings_offset = MAX_MATERIALS * FLOATS_PER_MATERIAL;
+            data[settings_offset] = if coloring.gradient.is_some() {
+                1.0
+            } else {
+                0.0
+            };
+
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.ubo_id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                utils::byte_size_of_array(&data),
+                utils::pointer_to_array(&data),
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+
+            if let Some(gradient) = &coloring.gradient {
+                let mut texels: Vec<f32> = Vec::with_capacity(GRADIENT_RESOLUTION * 3);
+                for texel in 0..GRADIENT_RESOLUTION {
+                    let t = texel as f32 / (GRADIENT_RESOLUTION - 1) as f32;
+                    texels.extend(gradient.evaluate(t));
+                }
+
+                gl::BindTexture(gl::TEXTURE_1D, self.gradient_texture_id);
+                gl::TexImage1D(
+                    gl::TEXTURE_1D,
+                    0,
+                    gl::RGB32F as i32,
+                    GRADIENT_RESOLUTION as i32,
+                    0,
+                    gl::RGB,
+                    gl::FLOAT,
+                    utils::pointer_to_array(&texels),
+                );
+                gl::BindTexture(gl::TEXTURE_1D, 0);
+            }
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, MATERIAL_BUFFER_BINDING, self.ubo_id);
+            gl::ActiveTexture(gl::TEXTURE0 + GRADIENT_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_1D, self.gradient_texture_id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+// Surface properties of a single terrain vertex that material rules are matched against
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainSample {
+    // Normalized noise height in [0, 1]
+    pub height: f32,
+    // Angle between the surface normal and the up vector, in degrees
+    pub slope: f32,
+    // Compass direction the slope faces, in degrees clockwise from north (-Z)
+    pub aspect: f32,
+    // Laplacian of the surface height, positive in valleys and negative on ridges
+    pub curvature: f32,
+}
+
+impl TerrainSample {
+    pub fn new(height: f32, normal: &glm::Vec3, curvature: f32) -> Self {
+        let slope = normal.y.clamp(-1.0, 1.0).acos().to_degrees();
+        let aspect = normal.x.atan2(-normal.z).to_degrees().rem_euclid(360.0);
+
+        Self {
+            height,
+            slope,
+            aspect,
+            curvature,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialRule {
+    pub enabled: bool,
+    pub priority: i32,
+    pub height_range: [f32; 2],
+    pub slope_range: [f32; 2],
+    pub aspect_enabled: bool,
+    pub aspect: f32,
+    pub aspect_tolerance: f32,
+    pub curvature_range: [f32; 2],
+}
+
+impl Default for MaterialRule {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl MaterialRule {
+    // A rule that is switched off, leaving the material to the height limit bands
+    pub fn disabled() -> Self {
+        MaterialRule {
+            enabled: false,
+            priority: 0,
+            height_range: [0.0, 1.0],
+            slope_range: [0.0, 90.0],
+            aspect_enabled: false,
+            aspect: 0.0,
+            aspect_tolerance: 45.0,
+            curvature_range: [-10.0, 10.0],
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::disabled()
+    }
+
+    pub fn matches(&self, sample: &TerrainSample) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        let in_range = |value: f32, range: [f32; 2]| value >= range[0] && value <= range[1];
+
+        if !in_range(sample.height, self.height_range)
+            || !in_range(sample.slope, self.slope_range)
+            || !in_range(sample.curvature, self.curvature_range)
+        {
+            return false;
+        }
+
+        if self.aspect_enabled {
+            // Flat ground doesn't face any direction
+            if sample.slope < 1.0 {
+                return false;
+            }
+            let difference = (sample.aspect - self.aspect).rem_euclid(360.0);
+            let angular_distance = difference.min(360.0 - difference);
+            if angular_distance > self.aspect_tolerance {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn render(&mut self, ui: &Ui, name: &str) {
+        ui.checkbox(format!("Use rule##{}", name), &mut self.enabled);
+        if !self.enabled {
+            return;
+        }
+
+        ui.slider(format!("Priority##{}", name), 0, 10, &mut self.priority);
+
+        ui.slider(
+            format!("Min height##{}", name),
+            0.0,
+            1.0,
+            &mut self.height_range[0],
+        );
+        ui.slider(
+            format!("Max height##{}", name),
+            0.0,
+            1.0,
+            &mut self.height_range[1],
+        );
+
+        ui.slider(
+            format!("Min slope##{}", name),
+            0.0,
+            90.0,
+            &mut self.slope_range[0],
+        );
+        ui.slider(
+            format!("Max slope##{}", name),
+            0.0,
+            90.0,
+            &mut self.slope_range[1],
+        );
+
+        ui.checkbox(format!("Use aspect##{}", name), &mut self.aspect_enabled);
+        if self.aspect_enabled {
+            ui.slider(format!("Aspect##{}", name), 0.0, 360.0, &mut self.aspect);
+            ui.slider(
+                format!("Aspect tolerance##{}", name),
+                0.0,
+                180.0,
+                &mut self.aspect_tolerance,
+            );
+        }
+
+        ui.slider(
+            format!("Min curvature##{}", name),
+            -10.0,
+            10.0,
+            &mut self.curvature_range[0],
+        );
+        ui.slider(
+            format!("Max curvature##{}", name),
+            -10.0,
+            10.0,
+            &mut self.curvature_range[1],
+        );
+    }
+}
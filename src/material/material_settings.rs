@@ -1,6 +1,9 @@
 use imgui::{CollapsingHeader, Ui};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq)]
+use super::material_rule::MaterialRule;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct MaterialSettings {
     pub name: String,
     pub ambient: [f32; 3],
@@ -8,6 +11,8 @@ pub struct MaterialSettings {
     pub specular: [f32; 3],
     pub shininess: f32,
     pub height_limit: f32,
+    #[serde(default, skip_serializing_if = "MaterialRule::is_default")]
+    pub rule: MaterialRule,
 }
 
 impl MaterialSettings {
@@ -18,6 +23,7 @@ impl MaterialSettings {
         specular: [f32; 3],
         shininess: f32,
         height_limit: f32,
+        rule: MaterialRule,
     ) -> MaterialSettings {
         MaterialSettings {
             name,
@@ -26,6 +32,19 @@ impl MaterialSettings {
             specular,
             shininess,
             height_limit,
+            rule,
+        }
+    }
+
+    pub fn standard_material() -> Self {
+        MaterialSettings {
+            name: "Material".to_string(),
+            ambient: [1.0, 0.7, 0.81],
+            diffuse: [1.0, 0.5, 0.31],
+            specular: [0.5, 0.5, 0.5],
+            shininess: 32.0,
+            height_limit: 0.5,
+            rule: MaterialRule::disabled(),
         }
     }
 
@@ -37,6 +56,7 @@ impl MaterialSettings {
             specular: [1.0, 1.0, 1.0],
             shininess: 16.0,
             height_limit: 0.4,
+            rule: MaterialRule::disabled(),
         }
     }
 
@@ -48,6 +68,7 @@ impl MaterialSettings {
             specular: [0.5, 0.5, 0.5],
             shininess: 2.0,
             height_limit: 0.43,
+            rule: MaterialRule::disabled(),
         }
     }
 
@@ -59,6 +80,7 @@ impl MaterialSettings {
             specular: [0.5, 0.5, 0.5],
             shininess: 2.0,
             height_limit: 0.8,
+            rule: MaterialRule::disabled(),
         }
     }
 
@@ -70,6 +92,25 @@ impl MaterialSettings {
             specular: [1.0, 1.0, 1.0],
             shininess: 32.0,
             height_limit: 1.0,
+            rule: MaterialRule::disabled(),
+        }
+    }
+
+    pub fn standard_rock_material() -> Self {
+        MaterialSettings {
+            name: "Rock".to_string(),
+            ambient: [0.45, 0.42, 0.4],
+            diffuse: [0.45, 0.42, 0.4],
+            specular: [0.3, 0.3, 0.3],
+            shininess: 8.0,
+            height_limit: 1.0,
+            rule: MaterialRule {
+                enabled: true,
+                priority: 1,
+                height_range: [0.43, 1.0],
+                slope_range: [35.0, 90.0],
+                ..MaterialRule::disabled()
+            },
         }
     }
 
@@ -144,6 +185,8 @@ impl MaterialSettings {
                 1.0,
                 &mut self.height_limit,
             );
+
+            self.rule.render(ui, &self.name);
         }
     }
 }
@@ -1,6 +1,14 @@
-use self::material_settings::MaterialSettings;
+use crate::gradient_editor::gradient::Gradient;
 
+use self::{
+    material_rule::{MaterialRule, TerrainSample},
+    material_settings::MaterialSettings,
+};
+
+pub mod material_buffer;
+pub mod material_rule;
 pub mod material_settings;
+pub mod palette;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Material {
@@ -9,6 +17,7 @@ pub struct Material {
     pub specular: [f32; 3],
     pub shininess: f32,
     pub height_limit: f32,
+    pub rule: MaterialRule,
 }
 
 impl Material {
@@ -19,6 +28,7 @@ impl Material {
             specular: settings.specular,
             shininess: settings.shininess,
             height_limit: settings.height_limit,
+            rule: settings.rule,
         }
     }
 
@@ -29,6 +39,37 @@ impl Material {
             specular: [0.5, 0.5, 0.5],
             shininess: 32.0,
             height_limit: 0.5,
+            rule: MaterialRule::disabled(),
         }
     }
+
+    // Returns the index of the matching rule with the highest priority, and falls back to
+    // the first height band the sample fits in. Materials are expected to be sorted by height limit.
+    pub fn select(materials: &[Material], sample: &TerrainSample) -> usize {
+        let mut selected: Option<usize> = None;
+        for (index, material) in materials.iter().enumerate() {
+            if material.rule.matches(sample)
+                && selected.is_none_or(|s| material.rule.priority > materials[s].rule.priority)
+            {
+                selected = Some(index);
+            }
+        }
+
+        selected
+            .or_else(|| {
+                materials
+                    .iter()
+                    .position(|material| sample.height <= material.height_limit)
+            })
+            .unwrap_or(materials.len().saturating_sub(1))
+    }
+}
+
+// Everything needed to colour the terrain. Vertices only store a material index,
+// the colours are looked up in the material buffer when drawing.
+#[derive(Clone)]
+pub struct TerrainColoring {
+    pub materials: Vec<Material>,
+    // Replaces the ambient and diffuse colour of the selected material when set
+    pub gradient: Option<Gradient>,
 }
@@ -0,0 +1,280 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use imgui::{DragDropFlags, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::gradient_editor::{gradient::Gradient, gradient_widget::GradientEditor};
+
+use super::{
+    material_buffer::MAX_MATERIALS, material_settings::MaterialSettings, Material, TerrainColoring,
+};
+
+const PALETTE_DRAG_DROP_ID: &str = "PALETTE_MATERIAL";
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColoringMode {
+    // Discrete materials chosen by height bands and rules
+    #[default]
+    Materials,
+    // Ambient and diffuse colour sampled from a gradient over normalized height
+    Gradient,
+}
+
+// An ordered list of materials, where the order defines the height bands
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    #[serde(default)]
+    pub mode: ColoringMode,
+    #[serde(default)]
+    pub gradient: Gradient,
+    #[serde(rename = "material")]
+    pub materials: Vec<MaterialSettings>,
+}
+
+impl Palette {
+    pub fn standard() -> Self {
+        Palette {
+            mode: ColoringMode::Materials,
+            gradient: Gradient::terrain(),
+            materials: vec![
+                MaterialSettings::standard_water_material(),
+                MaterialSettings::standard_sand_material(),
+                MaterialSettings::standard_grass_material(),
+                MaterialSettings::standard_snow_material(),
+                MaterialSettings::standard_rock_material(),
+            ],
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Palette, String> {
+        let palette: Palette = toml::from_str(source).map_err(|e| e.to_string())?;
+        palette.validate()?;
+        Ok(palette)
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        round_floats(&mut value);
+        toml::to_string(&value).map_err(|e| e.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.materials.is_empty() {
+            return Err("The palette must contain at least one material".to_string());
+        }
+        if self.materials.len() > MAX_MATERIALS {
+            return Err(format!(
+                "The palette can't contain more than {} materials",
+                MAX_MATERIALS
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for material in &self.materials {
+            if material.name.trim().is_empty() {
+                return Err("Material names can't be empty".to_string());
+            }
+            if !names.insert(material.name.as_str()) {
+                return Err(format!("Duplicate material name '{}'", material.name));
+            }
+            if !(0.0..=1.0).contains(&material.height_limit) {
+                return Err(format!(
+                    "Height limit of '{}' must be between 0 and 1",
+                    material.name
+                ));
+            }
+        }
+
+        for pair in self.materials.windows(2) {
+            if pair[1].height_limit < pair[0].height_limit {
+                return Err(format!(
+                    "Height limits must increase monotonically: '{}' ({}) comes after '{}' ({})",
+                    pair[1].name, pair[1].height_limit, pair[0].name, pair[0].height_limit
+                ));
+            }
+        }
+
+        if self.gradient.stops.len() < 2 {
+            return Err("The gradient needs at least two stops".to_string());
+        }
+        if self
+            .gradient
+            .stops
+            .iter()
+            .any(|stop| !(0.0..=1.0).contains(&stop.position))
+        {
+            return Err("Gradient stops must be between 0 and 1".to_string());
+        }
+
+        Ok(())
+    }
+
+    // Vertices store material indices chosen from the height bands and rules, so only
+    // changes to those need new meshes. Colour changes are picked up by the material buffer.
+    pub fn requires_rebuild(&self, other: &Palette) -> bool {
+        self.materials.len() != other.materials.len()
+            || self
+                .materials
+                .iter()
+                .zip(&other.materials)
+                .any(|(a, b)| a.height_limit != b.height_limit || a.rule != b.rule)
+    }
+
+    pub fn build_coloring(&self) -> TerrainColoring {
+        TerrainColoring {
+            materials: self.materials.iter().map(Material::new).collect(),
+            gradient: match self.mode {
+                ColoringMode::Materials => None,
+                ColoringMode::Gradient => Some(self.gradient.sorted()),
+            },
+        }
+    }
+
+    // Appends a number to the name until it doesn't collide with another material
+    fn unique_name(&self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut counter = 2;
+        while self.materials.iter().any(|m| m.name == name) {
+            name = format!("{} {}", base, counter);
+            counter += 1;
+        }
+        name
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        ui.radio_button("Materials", &mut self.mode, ColoringMode::Materials);
+        ui.same_line();
+        ui.radio_button("Gradient", &mut self.mode, ColoringMode::Gradient);
+
+        if self.mode == ColoringMode::Gradient {
+            GradientEditor::new("Height Gradient").render(ui, &mut self.gradient);
+            ui.text("Materials still provide specular and shininess");
+        }
+
+        let mut moved: Option<(usize, usize)> = None;
+        let mut duplicated: Option<usize> = None;
+        let mut deleted: Option<usize> = None;
+
+        for (index, material) in self.materials.iter_mut().enumerate() {
+            let _id = ui.push_id_usize(index);
+
+            ui.small_button("=");
+            if let Some(_tooltip) = ui
+                .drag_drop_source_config(PALETTE_DRAG_DROP_ID)
+                .begin_payload(index)
+            {
+                ui.text(&material.name);
+            }
+            if let Some(target) = ui.drag_drop_target() {
+                if let Some(Ok(payload)) =
+                    target.accept_payload::<usize, _>(PALETTE_DRAG_DROP_ID, DragDropFlags::empty())
+                {
+                    moved = Some((payload.data, index));
+                }
+                target.pop();
+            }
+
+            ui.same_line();
+            if ui.small_button("Duplicate") {
+                duplicated = Some(index);
+            }
+            ui.same_line();
+            if ui.small_button("Delete") {
+                deleted = Some(index);
+            }
+
+            material.render(ui);
+        }
+
+        if let Some((from, to)) = moved {
+            let material = self.materials.remove(from);
+            self.materials.insert(to, material);
+        }
+
+        if let Some(index) = duplicated {
+            let mut copy = self.materials[index].clone();
+            copy.name = self.unique_name(&copy.name);
+            self.materials.insert(index + 1, copy);
+        }
+
+        if let Some(index) = deleted {
+            self.materials.remove(index);
+        }
+
+        if ui.button("Add material") {
+            let mut material = MaterialSettings::standard_material();
+            material.name = self.unique_name("Material");
+            material.height_limit = self.materials.last().map_or(1.0, |m| m.height_limit);
+            self.materials.push(material);
+        }
+    }
+}
+
+// Settings are stored as f32, so print the shortest f32 representation
+// instead of writing out the widened f64 (0.3 rather than 0.30000001192092896)
+fn round_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => {
+            *float = (*float as f32).to_string().parse().unwrap_or(*float);
+        }
+        toml::Value::Array(array) => array.iter_mut().for_each(round_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| round_floats(v)),
+        _ => {}
+    }
+}
+
+// Tracks a palette file on disk so it can be reloaded when it changes
+pub struct PaletteFile {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl PaletteFile {
+    pub fn new(path: &str) -> Self {
+        PaletteFile {
+            path: PathBuf::from(path),
+            last_modified: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    pub fn load(&mut self) -> Result<Palette, String> {
+        self.last_modified = self.modified_time();
+        let source = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        Palette::from_toml(&source).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    pub fn save(&mut self, palette: &Palette) -> Result<(), String> {
+        palette.validate()?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&self.path, palette.to_toml()?)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        self.last_modified = self.modified_time();
+        Ok(())
+    }
+
+    // Returns the reloaded palette if the file was modified since it was last read or written
+    pub fn poll(&mut self) -> Option<Result<Palette, String>> {
+        let modified = self.modified_time();
+        if modified.is_none() || modified == self.last_modified {
+            return None;
+        }
+        Some(self.load())
+    }
+}
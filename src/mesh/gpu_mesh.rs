@@ -0,0 +1,97 @@
+use std::ptr;
+
+use crate::{aabb::Aabb, utils};
+
+use super::Mesh;
+
+// A mesh in its own vertex array and buffers, for the few meshes drawn outside the mesh pool
+pub struct GpuMesh {
+    vao_id: u32,
+    buffer_ids: [u32; 2],
+    index_count: i32,
+    // GL_UNSIGNED_SHORT when every index fits in 16 bits, otherwise GL_UNSIGNED_INT
+    index_type: u32,
+    // Local space bounds of the vertices
+    pub bounds: Aabb,
+}
+
+impl GpuMesh {
+    pub fn new(mesh: &Mesh) -> Self {
+        let index_type = if mesh.has_short_indices() {
+            gl::UNSIGNED_SHORT
+        } else {
+            gl::UNSIGNED_INT
+        };
+
+        let mut vao_id: u32 = 0;
+        let mut buffer_ids = [0; 2];
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao_id);
+            gl::BindVertexArray(vao_id);
+            gl::GenBuffers(buffer_ids.len() as i32, buffer_ids.as_mut_ptr());
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer_ids[0]);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                utils::byte_size_of_array(&mesh.vertex_data),
+                utils::pointer_to_array(&mesh.vertex_data),
+                gl::STATIC_DRAW,
+            );
+            mesh.layout.apply();
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer_ids[1]);
+            if index_type == gl::UNSIGNED_SHORT {
+                let short_indices: Vec<u16> = mesh.indices.iter().map(|&i| i as u16).collect();
+                gl::BufferData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    utils::byte_size_of_array(&short_indices),
+                    utils::pointer_to_array(&short_indices),
+                    gl::STATIC_DRAW,
+                );
+            } else {
+                gl::BufferData(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    utils::byte_size_of_array(&mesh.indices),
+                    utils::pointer_to_array(&mesh.indices),
+                    gl::STATIC_DRAW,
+                );
+            }
+            gl::BindVertexArray(0);
+        }
+
+        GpuMesh {
+            vao_id,
+            buffer_ids,
+            index_count: mesh.indices.len() as i32,
+            index_type,
+            bounds: mesh.bounds,
+        }
+    }
+
+    // Binds the vertex array, draw with draw_elements while it stays bound
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao_id);
+        }
+    }
+
+    pub fn draw_elements(&self) {
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                self.index_count,
+                self.index_type,
+                ptr::null(),
+            );
+        }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(self.buffer_ids.len() as i32, self.buffer_ids.as_ptr());
+            gl::DeleteVertexArrays(1, &self.vao_id);
+        }
+    }
+}
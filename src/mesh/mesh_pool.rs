@@ -0,0 +1,461 @@
+use std::{
+    ptr,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use crate::{scenenode::SceneNode, utils, vertex_layout::VertexLayout};
+
+use super::{range_allocator::RangeAllocator, Mesh};
+
+// Must match chunk_draws.glsl
+const DRAW_BUFFER_BINDING: u32 = 3;
+const DRAW_INDEX_LOCATION: u32 = 5;
+// The model matrix followed by the level of detail, padded to the struct's 16 byte alignment
+const FLOATS_PER_DRAW: usize = 20;
+// The values of a DrawElementsIndirectCommand
+const VALUES_PER_ELEMENTS_COMMAND: usize = 5;
+
+// Room for a full detail chunk with every level of detail, the buffers grow from there. Only
+// the full detail mesh has too many vertices for 16-bit indices.
+const INITIAL_VERTEX_CAPACITY: usize = 320_000;
+const INITIAL_INDEX_CAPACITY: usize = 1_400_000;
+const INITIAL_SHORT_INDEX_CAPACITY: usize = 500_000;
+
+// Where one mesh lives in the pool's buffers, in vertices and indices
+#[derive(Clone, Copy)]
+pub struct PoolRange {
+    pub first_vertex: usize,
+    pub vertex_count: usize,
+    pub first_index: usize,
+    pub index_count: usize,
+    // Whether the indices are in the 16-bit index buffer
+    pub short_indices: bool,
+}
+
+// A mesh uploaded to a pool. Dropping it hands its ranges back to the pool, which reuses them
+// from the next upload on.
+pub struct PooledMesh {
+    range: PoolRange,
+    released: Sender<PoolRange>,
+}
+
+impl PooledMesh {
+    pub fn range(&self) -> PoolRange {
+        self.range
+    }
+}
+
+impl Drop for PooledMesh {
+    fn drop(&mut self) {
+        // A pool that is gone took its buffers with it, there is nothing to hand back
+        let _ = self.released.send(self.range);
+    }
+}
+
+// One GL buffer split into ranges of elements
+struct PoolBuffer {
+    buffer_id: u32,
+    element_size: usize,
+    allocator: RangeAllocator,
+}
+
+impl PoolBuffer {
+    fn new(element_size: usize, capacity: usize) -> Self {
+        let mut buffer_id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_id);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer_id);
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (capacity * element_size) as isize,
+                ptr::null(),
+                gl::STATIC_DRAW,
+            );
+        }
+        PoolBuffer {
+            buffer_id,
+            element_size,
+            allocator: RangeAllocator::new(capacity),
+        }
+    }
+
+    // Returns the first element of the range and whether the buffer had to grow, which
+    // replaces it and leaves the vertex array pointing at the old one
+    fn allocate(&mut self, count: usize) -> (usize, bool) {
+        if let Some(range) = self.allocator.allocate(count) {
+            return (range.start, false);
+        }
+
+        let old_capacity = self.allocator.capacity();
+        let capacity = (old_capacity * 2).max(old_capacity + count);
+        let mut buffer_id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_id);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer_id);
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (capacity * self.element_size) as isize,
+                ptr::null(),
+                gl::STATIC_DRAW,
+            );
+            gl::BindBuffer(gl::COPY_READ_BUFFER, self.buffer_id);
+            gl::CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                0,
+                0,
+                (old_capacity * self.element_size) as isize,
+            );
+            gl::DeleteBuffers(1, &self.buffer_id);
+        }
+        self.buffer_id = buffer_id;
+        self.allocator.grow(capacity);
+
+        let range = self
+            .allocator
+            .allocate(count)
+            .expect("grown buffer has room for the range");
+        (range.start, true)
+    }
+
+    // Bytes the buffer would copy into a larger one to fit count more elements
+    fn growth_cost(&self, count: usize) -> usize {
+        if self.allocator.can_allocate(count) {
+            0
+        } else {
+            self.allocator.capacity() * self.element_size
+        }
+    }
+
+    fn write<T>(&self, first_element: usize, data: &[T]) {
+        if data.is_empty() {
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.buffer_id);
+            gl::BufferSubData(
+                gl::COPY_WRITE_BUFFER,
+                (first_element * self.element_size) as isize,
+                utils::byte_size_of_array(data),
+                utils::pointer_to_array(data),
+            );
+        }
+    }
+
+    fn byte_sizes(&self) -> (usize, usize) {
+        (
+            self.allocator.used() * self.element_size,
+            self.allocator.capacity() * self.element_size,
+        )
+    }
+}
+
+// The meshes of every loaded chunk in one shared vertex and index buffer, so all of a scene
+// is drawn with one indirect call per index type. Indices are relative to the mesh's first
+// vertex, and meshes with few enough vertices keep them in a separate 16-bit index buffer.
+pub struct MeshPool {
+    layout: VertexLayout,
+    vao_id: u32,
+    vertex_buffer: PoolBuffer,
+    index_buffer: PoolBuffer,
+    short_index_buffer: PoolBuffer,
+
+    // Holds 0, 1, 2... read once per instance. Each draw command starts its instances at its
+    // own index, which tells the shader where its draw data is.
+    draw_index_buffer_id: u32,
+    draw_index_capacity: usize,
+    draw_buffer_id: u32,
+    indirect_buffer_id: u32,
+
+    release_sender: Sender<PoolRange>,
+    released: Receiver<PoolRange>,
+}
+
+impl MeshPool {
+    pub fn new(layout: VertexLayout) -> Self {
+        let (release_sender, released) = mpsc::channel();
+        let mut ids = [0; 3];
+        unsafe {
+            gl::GenBuffers(ids.len() as i32, ids.as_mut_ptr());
+        }
+
+        let mut pool = MeshPool {
+            vertex_buffer: PoolBuffer::new(layout.stride(), INITIAL_VERTEX_CAPACITY),
+            index_buffer: PoolBuffer::new(std::mem::size_of::<u32>(), INITIAL_INDEX_CAPACITY),
+            short_index_buffer: PoolBuffer::new(
+                std::mem::size_of::<u16>(),
+                INITIAL_SHORT_INDEX_CAPACITY,
+            ),
+            layout,
+            vao_id: 0,
+            draw_index_buffer_id: ids[0],
+            draw_index_capacity: 0,
+            draw_buffer_id: ids[1],
+            indirect_buffer_id: ids[2],
+            release_sender,
+            released,
+        };
+        unsafe {
+            gl::GenVertexArrays(1, &mut pool.vao_id);
+        }
+        pool.bind_buffers();
+        pool
+    }
+
+    // Bytes uploading the mesh would write, including the copies of buffers that have to grow
+    // to fit it
+    pub fn upload_cost(&mut self, mesh: &Mesh) -> usize {
+        self.free_released();
+        mesh.byte_size()
+            + self.vertex_buffer.growth_cost(mesh.vertex_count)
+            + self
+                .index_buffer(mesh.has_short_indices())
+                .growth_cost(mesh.indices.len())
+    }
+
+    pub fn upload(&mut self, mesh: &Mesh) -> PooledMesh {
+        debug_assert!(mesh.layout == self.layout);
+        self.free_released();
+
+        let short_indices = mesh.has_short_indices();
+        let (first_vertex, vertices_grown) = self.vertex_buffer.allocate(mesh.vertex_count);
+        let index_buffer = self.index_buffer_mut(short_indices);
+        let (first_index, _) = index_buffer.allocate(mesh.indices.len());
+        if short_indices {
+            let indices: Vec<u16> = mesh.indices.iter().map(|&i| i as u16).collect();
+            index_buffer.write(first_index, &indices);
+        } else {
+            index_buffer.write(first_index, &mesh.indices);
+        }
+        // The index buffers are bound per draw, only the vertex array points at the vertices
+        if vertices_grown {
+            self.bind_buffers();
+        }
+        self.vertex_buffer.write(first_vertex, &mesh.vertex_data);
+
+        PooledMesh {
+            range: PoolRange {
+                first_vertex,
+                vertex_count: mesh.vertex_count,
+                first_index,
+                index_count: mesh.indices.len(),
+                short_indices,
+            },
+            released: self.release_sender.clone(),
+        }
+    }
+
+    // Bytes of the vertex and index buffers in use and allocated
+    pub fn usage(&mut self) -> (usize, usize) {
+        self.free_released();
+        let (vertices_used, vertices_allocated) = self.vertex_buffer.byte_sizes();
+        let (indices_used, indices_allocated) = self.index_buffer.byte_sizes();
+        let (short_used, short_allocated) = self.short_index_buffer.byte_sizes();
+        (
+            vertices_used + indices_used + short_used,
+            vertices_allocated + indices_allocated + short_allocated,
+        )
+    }
+
+    // Draws the triangles of every node that has a mesh in the pool with the active program
+    pub fn draw(&mut self, nodes: &[SceneNode]) {
+        let ranges = self.bind_draws(nodes);
+        if ranges.is_empty() {
+            return;
+        }
+
+        // DrawElementsIndirectCommand: count, instance count, first index, base vertex and
+        // base instance. The first index counts elements of the command's index type. The
+        // 32-bit commands come first, each index type is drawn with its own call.
+        let mut long_commands = Vec::new();
+        let mut short_commands = Vec::new();
+        for (draw_index, range) in ranges.iter().enumerate() {
+            let commands = if range.short_indices {
+                &mut short_commands
+            } else {
+                &mut long_commands
+            };
+            commands.extend([
+                range.index_count as u32,
+                1,
+                range.first_index as u32,
+                range.first_vertex as u32,
+                draw_index as u32,
+            ]);
+        }
+        let long_count = long_commands.len() / VALUES_PER_ELEMENTS_COMMAND;
+        let short_count = short_commands.len() / VALUES_PER_ELEMENTS_COMMAND;
+        long_commands.append(&mut short_commands);
+        unsafe {
+            self.upload_commands(&long_commands);
+            if long_count > 0 {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer.buffer_id);
+                gl::MultiDrawElementsIndirect(
+                    gl::TRIANGLES,
+                    gl::UNSIGNED_INT,
+                    ptr::null(),
+                    long_count as i32,
+                    0,
+                );
+            }
+            if short_count > 0 {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.short_index_buffer.buffer_id);
+                gl::MultiDrawElementsIndirect(
+                    gl::TRIANGLES,
+                    gl::UNSIGNED_SHORT,
+                    (long_count * VALUES_PER_ELEMENTS_COMMAND * std::mem::size_of::<u32>())
+                        as *const _,
+                    short_count as i32,
+                    0,
+                );
+            }
+            gl::BindVertexArray(0);
+        }
+    }
+
+    // Draws every vertex of the nodes as a point, for the normal lines
+    pub fn draw_points(&mut self, nodes: &[SceneNode]) {
+        let ranges = self.bind_draws(nodes);
+        if ranges.is_empty() {
+            return;
+        }
+
+        // DrawArraysIndirectCommand: count, instance count, first vertex and base instance
+        let commands: Vec<u32> = ranges
+            .iter()
+            .enumerate()
+            .flat_map(|(draw_index, range)| {
+                [
+                    range.vertex_count as u32,
+                    1,
+                    range.first_vertex as u32,
+                    draw_index as u32,
+                ]
+            })
+            .collect();
+        unsafe {
+            self.upload_commands(&commands);
+            gl::MultiDrawArraysIndirect(gl::POINTS, ptr::null(), ranges.len() as i32, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    fn free_released(&mut self) {
+        while let Ok(range) = self.released.try_recv() {
+            self.vertex_buffer
+                .allocator
+                .free(range.first_vertex..range.first_vertex + range.vertex_count);
+            self.index_buffer_mut(range.short_indices)
+                .allocator
+                .free(range.first_index..range.first_index + range.index_count);
+        }
+    }
+
+    fn index_buffer(&self, short_indices: bool) -> &PoolBuffer {
+        if short_indices {
+            &self.short_index_buffer
+        } else {
+            &self.index_buffer
+        }
+    }
+
+    fn index_buffer_mut(&mut self, short_indices: bool) -> &mut PoolBuffer {
+        if short_indices {
+            &mut self.short_index_buffer
+        } else {
+            &mut self.index_buffer
+        }
+    }
+
+    // Points the vertex array at the current buffers
+    fn bind_buffers(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer.buffer_id);
+            self.layout.apply();
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.draw_index_buffer_id);
+            gl::VertexAttribIPointer(DRAW_INDEX_LOCATION, 1, gl::UNSIGNED_INT, 0, ptr::null());
+            gl::VertexAttribDivisor(DRAW_INDEX_LOCATION, 1);
+            gl::EnableVertexAttribArray(DRAW_INDEX_LOCATION);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    // Uploads the model matrix and level of detail of the nodes with a mesh and binds what
+    // drawing them needs. Returns their ranges in draw order.
+    fn bind_draws(&mut self, nodes: &[SceneNode]) -> Vec<PoolRange> {
+        let drawn: Vec<(&SceneNode, PoolRange)> = nodes
+            .iter()
+            .filter_map(|node| Some((node, node.mesh?)))
+            .collect();
+        if drawn.is_empty() {
+            return Vec::new();
+        }
+
+        let mut data = vec![0.0f32; drawn.len() * FLOATS_PER_DRAW];
+        for (values, (node, _)) in data.chunks_exact_mut(FLOATS_PER_DRAW).zip(&drawn) {
+            // Transposed like the matrix uniforms, so the shaders multiply the same way
+            values[0..16].copy_from_slice(glm::transpose(&node.model_matrix()).as_slice());
+            values[16] = f32::from_bits(node.lod_index as u32);
+        }
+
+        unsafe {
+            if drawn.len() > self.draw_index_capacity {
+                self.draw_index_capacity = drawn.len().next_power_of_two();
+                let draw_indices: Vec<u32> = (0..self.draw_index_capacity as u32).collect();
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.draw_index_buffer_id);
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    utils::byte_size_of_array(&draw_indices),
+                    utils::pointer_to_array(&draw_indices),
+                    gl::STATIC_DRAW,
+                );
+            }
+
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.draw_buffer_id);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                utils::byte_size_of_array(&data),
+                utils::pointer_to_array(&data),
+                gl::STREAM_DRAW,
+            );
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                DRAW_BUFFER_BINDING,
+                self.draw_buffer_id,
+            );
+            gl::BindVertexArray(self.vao_id);
+        }
+
+        drawn.into_iter().map(|(_, range)| range).collect()
+    }
+
+    unsafe fn upload_commands(&self, commands: &[u32]) {
+        gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.indirect_buffer_id);
+        gl::BufferData(
+            gl::DRAW_INDIRECT_BUFFER,
+            utils::byte_size_of_array(commands),
+            utils::pointer_to_array(commands),
+            gl::STREAM_DRAW,
+        );
+    }
+}
+
+impl Drop for MeshPool {
+    fn drop(&mut self) {
+        let buffer_ids = [
+            self.vertex_buffer.buffer_id,
+            self.index_buffer.buffer_id,
+            self.short_index_buffer.buffer_id,
+            self.draw_index_buffer_id,
+            self.draw_buffer_id,
+            self.indirect_buffer_id,
+        ];
+        unsafe {
+            gl::DeleteBuffers(buffer_ids.len() as i32, buffer_ids.as_ptr());
+            gl::DeleteVertexArrays(1, &self.vao_id);
+        }
+    }
+}
@@ -8,7 +8,7 @@ pub struct MeshSettings {
     pub strength: f32,
     pub curve: Curve,
     pub level_of_detail: i32,
-    
+    pub compact_vertices: bool,
 }
 
 impl MeshSettings {
@@ -18,6 +18,7 @@ impl MeshSettings {
             strength,
             curve,
             level_of_detail: level_of_detail.clamp(0, 6),
+            compact_vertices: false,
         }
     }
 
@@ -30,6 +31,7 @@ impl MeshSettings {
                 &mut self.strength,
             );
             ui.slider("Detail", 0, 6, &mut self.level_of_detail);
+            ui.checkbox("Compact vertices", &mut self.compact_vertices);
 
             CurveEditor::new("Terrain Curve Editor").render(ui, &mut self.curve);
         }
@@ -1,45 +1,47 @@
-pub mod mesh_material;
+pub mod gpu_mesh;
+pub mod mesh_pool;
 pub mod mesh_settings;
-
-use std::ptr;
+pub mod range_allocator;
+pub mod vertex_cache;
 
 use crate::{
-    material::Material,
-    noise_map::{noise_map_settings::NoiseMapSettings, NoiseMap},
+    aabb::Aabb,
+    height_field::HeightField,
+    material::{material_rule::TerrainSample, Material, TerrainColoring},
     triangle::Triangle,
-    utils,
     vertex::Vertex,
+    vertex_layout::{AttributeType, VertexAttribute, VertexLayout},
     CHUNK_PIXEL_SIZE,
 };
 
-use self::{mesh_material::MeshMaterial, mesh_settings::MeshSettings};
+use self::mesh_settings::MeshSettings;
+
+// Position of the material index in the terrain vertex layout
+const MATERIAL_INDEX_ATTRIBUTE: usize = 2;
 
 #[derive(Clone)]
 pub struct Mesh {
-    pub vertices: Vec<f32>,
+    // Interleaved vertex attributes, described by the layout
+    pub vertex_data: Vec<u8>,
+    pub layout: VertexLayout,
+    pub vertex_count: usize,
     pub indices: Vec<u32>,
-    pub normals: Vec<f32>,
-
-    pub material: MeshMaterial,
-
-    pub index_count: i32,
+    // Local space bounds of the vertices
+    pub bounds: Aabb,
 
-    pub vao_id: u32,
-
-    buffer_ids: Vec<u32>,
+    // Average cache miss ratio before and after reordering the triangles
+    pub acmr_before: f32,
+    pub acmr_after: f32,
 }
 
 impl Mesh {
     pub fn create_terrain_mesh(
-        materials: &Vec<Material>,
-        noise_map_settings: &NoiseMapSettings,
+        coloring: &TerrainColoring,
+        height_field: &HeightField,
         settings: &MeshSettings,
     ) -> Mesh {
         let map_chunk_size = CHUNK_PIXEL_SIZE + 1;
 
-        let noise_map = NoiseMap::new(*noise_map_settings);
-        let height_map = noise_map.get_height_map();
-
         let mesh_simplification_increment = if settings.level_of_detail == 0 {
             1
         } else {
@@ -51,8 +53,6 @@ impl Mesh {
         let mut shape_vertices: Vec<Vertex> = Vec::new();
         let mut shape_triangles: Vec<Triangle> = Vec::new();
 
-        let mut vertices: Vec<f32> = Vec::new();
-        let mut normals: Vec<f32> = Vec::new();
         let mut indices: Vec<u32> = Vec::new();
 
         let top_left_x = (map_chunk_size - 1) as f32 / -2.0;
@@ -60,27 +60,22 @@ impl Mesh {
 
         let mut vertex_index = 0;
 
+        let mut positions: Vec<glm::Vec3> = Vec::new();
+        let mut noise_heights: Vec<f32> = Vec::new();
+        let mut occlusion: Vec<f32> = Vec::new();
+
         for z in (0..map_chunk_size).step_by(mesh_simplification_increment as usize) {
             for x in (0..map_chunk_size).step_by(mesh_simplification_increment as usize) {
-                let noise_height = height_map[x as usize][z as usize] as f32;
-                let vertex_height =
-                    settings.curve.evaluate(noise_height as f64) as f32 * settings.strength;
-
-                for material in materials.iter() {
-                    if noise_height <= material.height_limit {
-                        let vertex = Vertex {
-                            position: glm::vec3(
-                                top_left_x + x as f32,
-                                vertex_height,
-                                top_left_z - z as f32,
-                            ),
-                            material: *material,
-                        };
-
-                        shape_vertices.push(vertex);
-                        break;
-                    }
-                }
+                let noise_height = height_field.noise_height(x as usize, z as usize);
+                let vertex_height = height_field.height(x, z);
+
+                positions.push(glm::vec3(
+                    top_left_x + x as f32,
+                    vertex_height,
+                    top_left_z - z as f32,
+                ));
+                noise_heights.push(noise_height);
+                occlusion.push(height_field.occlusion(x as usize, z as usize));
 
                 if x < map_chunk_size - 1 && z < map_chunk_size - 1 {
                     let triangle_1 = Triangle::new(
@@ -101,16 +96,11 @@ impl Mesh {
                 vertex_index += 1;
             }
         }
-        let mut vertex_normals: Vec<glm::Vec3> =
-            vec![glm::vec3(0.0, 0.0, 0.0); shape_vertices.len()];
+        let mut vertex_normals: Vec<glm::Vec3> = vec![glm::vec3(0.0, 0.0, 0.0); positions.len()];
 
         for triangle in &shape_triangles {
-            let vertex_1 = shape_vertices[triangle.a];
-            let vertex_2 = shape_vertices[triangle.b];
-            let vertex_3 = shape_vertices[triangle.c];
-
-            let ab = vertex_2.position - vertex_1.position;
-            let ac = vertex_3.position - vertex_1.position;
+            let ab = positions[triangle.b] - positions[triangle.a];
+            let ac = positions[triangle.c] - positions[triangle.a];
             let triangle_normal = glm::cross(&ab, &ac);
 
             vertex_normals[triangle.a] += triangle_normal;
@@ -118,215 +108,138 @@ impl Mesh {
             vertex_normals[triangle.c] += triangle_normal;
         }
 
-        for normal in &vertex_normals {
-            let normalized_normal = glm::normalize(normal);
-            normals.extend(&normalized_normal);
+        for normal in vertex_normals.iter_mut() {
+            *normal = glm::normalize(normal);
+        }
+
+        let line_length = vertices_per_line as usize;
+        let grid_spacing = mesh_simplification_increment as f32;
+
+        for (index, position) in positions.iter().enumerate() {
+            let (x, z) = (index % line_length, index / line_length);
+
+            // Discrete Laplacian over the vertex grid, clamped at the chunk border
+            let height_at = |x: usize, z: usize| positions[z * line_length + x].y;
+            let curvature = (height_at(x.saturating_sub(1), z)
+                + height_at((x + 1).min(line_length - 1), z)
+                + height_at(x, z.saturating_sub(1))
+                + height_at(x, (z + 1).min(line_length - 1))
+                - 4.0 * position.y)
+                / (grid_spacing * grid_spacing);
+
+            let sample =
+                TerrainSample::new(noise_heights[index], &vertex_normals[index], curvature);
+
+            shape_vertices.push(Vertex::new(
+                *position,
+                vertex_normals[index],
+                Material::select(&coloring.materials, &sample) as u32,
+                noise_heights[index],
+                occlusion[index],
+            ));
         }
 
         for triangle in &shape_triangles {
             indices.extend_from_slice(&[triangle.a as u32, triangle.b as u32, triangle.c as u32]);
         }
 
+        let acmr_before = vertex_cache::acmr(&indices, vertex_cache::SIMULATED_CACHE_SIZE);
+        let indices = vertex_cache::optimize(&indices, shape_vertices.len());
+        let acmr_after = vertex_cache::acmr(&indices, vertex_cache::SIMULATED_CACHE_SIZE);
+
+        let mut bounds = Aabb::empty();
         for vertex in &shape_vertices {
-            vertices.extend(&vertex.position);
+            bounds.grow(&vertex.position);
         }
 
-        let mut mesh_material = MeshMaterial {
-            ambient: Vec::new(),
-            diffuse: Vec::new(),
-            specular: Vec::new(),
-            shininess: Vec::new(),
-        };
+        let layout = Mesh::terrain_vertex_layout(settings.compact_vertices);
+        let mut vertex_data: Vec<u8> = Vec::with_capacity(layout.stride() * shape_vertices.len());
 
         for vertex in &shape_vertices {
-            mesh_material.ambient.extend(&vertex.material.ambient);
-            mesh_material.diffuse.extend(&vertex.material.diffuse);
-            mesh_material.specular.extend(&vertex.material.specular);
-            mesh_material.shininess.push(vertex.material.shininess);
+            layout.push_vertex(
+                &mut vertex_data,
+                &[
+                    vertex.position.as_slice(),
+                    vertex.normal.as_slice(),
+                    &[vertex.material_index as f32],
+                    &[vertex.height],
+                    &[vertex.occlusion],
+                ],
+            );
         }
 
-        let mesh = Mesh {
-            vertices,
+        Mesh {
+            vertex_data,
+            layout,
+            vertex_count: shape_vertices.len(),
             indices,
-            normals,
-            material: mesh_material,
+            bounds,
 
-            index_count: shape_triangles.len() as i32 * 3,
+            acmr_before,
+            acmr_after,
+        }
+    }
+
+    // A mesh of already interleaved vertices, without vertex cache statistics
+    pub fn new(
+        vertex_data: Vec<u8>,
+        layout: VertexLayout,
+        vertex_count: usize,
+        indices: Vec<u32>,
+        bounds: Aabb,
+    ) -> Mesh {
+        Mesh {
+            vertex_data,
+            layout,
+            vertex_count,
+            indices,
+            bounds,
+
+            acmr_before: 0.0,
+            acmr_after: 0.0,
+        }
+    }
 
-            vao_id: 0,
-            buffer_ids: Vec::new(),
+    // Whether every index fits in 16 bits, which halves the size of the index buffer
+    pub fn has_short_indices(&self) -> bool {
+        self.vertex_count <= u16::MAX as usize + 1
+    }
+
+    // Bytes the vertices and indices take up in the mesh pool
+    pub fn byte_size(&self) -> usize {
+        let index_size = if self.has_short_indices() {
+            std::mem::size_of::<u16>()
+        } else {
+            std::mem::size_of::<u32>()
         };
-        mesh
+        self.vertex_data.len() + self.indices.len() * index_size
     }
 
-    pub fn delete_buffers(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(self.buffer_ids.len() as i32, self.buffer_ids.as_ptr());
-        }
+    pub fn material_index(&self, vertex: usize) -> u32 {
+        self.layout
+            .read_integer(&self.vertex_data, vertex, MATERIAL_INDEX_ATTRIBUTE)
     }
-    pub unsafe fn create_vao(&mut self) -> u32 {
-        let mut vao_ids: u32 = 0;
-        gl::GenVertexArrays(1, &mut vao_ids as *mut u32);
-        gl::BindVertexArray(vao_ids);
-
-        let mut vbo_ids: u32 = 0;
-        gl::GenBuffers(1, &mut vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.vertices),
-            utils::pointer_to_array(&self.vertices),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(vbo_ids);
-
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 3,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(0);
-
-        //Ambient buffer
-        let mut ambient_vbo_ids: u32 = 1;
-        gl::GenBuffers(1, &mut ambient_vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, ambient_vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.material.ambient),
-            utils::pointer_to_array(&self.material.ambient),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(ambient_vbo_ids);
-
-        gl::VertexAttribPointer(
-            1,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 3,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(1);
-
-        //Diffuse buffer
-        let mut diffuse_vbo_ids: u32 = 2;
-        gl::GenBuffers(1, &mut diffuse_vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, diffuse_vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.material.diffuse),
-            utils::pointer_to_array(&self.material.diffuse),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(diffuse_vbo_ids);
-
-        gl::VertexAttribPointer(
-            2,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 3,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(2);
-
-        //Specular buffer
-        let mut specular_vbo_ids: u32 = 3;
-        gl::GenBuffers(1, &mut specular_vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, specular_vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.material.specular),
-            utils::pointer_to_array(&self.material.specular),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(specular_vbo_ids);
-
-        gl::VertexAttribPointer(
-            3,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 3,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(3);
-
-        //Shininess buffer
-        let mut shininess_vbo_ids: u32 = 4;
-        gl::GenBuffers(1, &mut shininess_vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, shininess_vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.material.shininess),
-            utils::pointer_to_array(&self.material.shininess),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(shininess_vbo_ids);
-
-        gl::VertexAttribPointer(
-            4,
-            1,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 1,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(4);
-
-        //Normal buffer
-        let mut normvec_vbo_ids: u32 = 5;
-        gl::GenBuffers(1, &mut normvec_vbo_ids as *mut u32);
-        gl::BindBuffer(gl::ARRAY_BUFFER, normvec_vbo_ids);
-
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.normals),
-            utils::pointer_to_array(&self.normals),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(normvec_vbo_ids);
-
-        gl::VertexAttribPointer(
-            5,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            utils::size_of::<f32>() * 3,
-            ptr::null(),
-        );
-        gl::EnableVertexAttribArray(5);
-
-        let mut ibo_ids: u32 = 0;
-        gl::GenBuffers(1, &mut ibo_ids as *mut u32);
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo_ids);
-
-        // * Fill it with data
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            utils::byte_size_of_array(&self.indices),
-            utils::pointer_to_array(&self.indices),
-            gl::STATIC_DRAW,
-        );
-
-        self.buffer_ids.push(ibo_ids);
-
-        self.vao_id = vao_ids;
-        vao_ids
+
+    // Compact vertices pack the normal into 32 bits and store the material index, height
+    // and occlusion in 16 bits each, shrinking a vertex from 36 to 24 bytes padded to whole
+    // words
+    pub fn terrain_vertex_layout(compact: bool) -> VertexLayout {
+        if compact {
+            VertexLayout::new(vec![
+                VertexAttribute::new("position", 0, 3, AttributeType::Float, false),
+                VertexAttribute::new("normalVector", 1, 3, AttributeType::Int2101010Rev, true),
+                VertexAttribute::new("material_index", 2, 1, AttributeType::UnsignedShort, false),
+                VertexAttribute::new("height", 3, 1, AttributeType::HalfFloat, false),
+                VertexAttribute::new("occlusion", 4, 1, AttributeType::HalfFloat, false),
+            ])
+        } else {
+            VertexLayout::new(vec![
+                VertexAttribute::new("position", 0, 3, AttributeType::Float, false),
+                VertexAttribute::new("normalVector", 1, 3, AttributeType::Float, false),
+                VertexAttribute::new("material_index", 2, 1, AttributeType::UnsignedInt, false),
+                VertexAttribute::new("height", 3, 1, AttributeType::Float, false),
+                VertexAttribute::new("occlusion", 4, 1, AttributeType::Float, false),
+            ])
+        }
     }
 }
@@ -0,0 +1,170 @@
+use std::ops::Range;
+
+// Hands out ranges of a buffer of capacity elements, first fit. Freed ranges are merged with
+// their free neighbours so the buffer doesn't fragment into pieces too small to reuse.
+pub struct RangeAllocator {
+    capacity: usize,
+    // Sorted by start, never touching each other
+    free_ranges: Vec<Range<usize>>,
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: usize) -> Self {
+        let mut allocator = RangeAllocator {
+            capacity: 0,
+            free_ranges: Vec::new(),
+        };
+        allocator.grow(capacity);
+        allocator
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn used(&self) -> usize {
+        self.capacity
+            - self
+                .free_ranges
+                .iter()
+                .map(|range| range.len())
+                .sum::<usize>()
+    }
+
+    // Whether allocate would find a free range of size
+    pub fn can_allocate(&self, size: usize) -> bool {
+        size == 0 || self.free_ranges.iter().any(|range| range.len() >= size)
+    }
+
+    // None when no free range is large enough, the caller can grow the buffer and try again
+    pub fn allocate(&mut self, size: usize) -> Option<Range<usize>> {
+        if size == 0 {
+            return Some(0..0);
+        }
+
+        let index = self
+            .free_ranges
+            .iter()
+            .position(|range| range.len() >= size)?;
+        let free_range = &mut self.free_ranges[index];
+        let allocated = free_range.start..free_range.start + size;
+        free_range.start += size;
+        if free_range.start == free_range.end {
+            self.free_ranges.remove(index);
+        }
+        Some(allocated)
+    }
+
+    pub fn free(&mut self, range: Range<usize>) {
+        if range.start == range.end {
+            return;
+        }
+        debug_assert!(range.end <= self.capacity);
+
+        let index = self
+            .free_ranges
+            .partition_point(|free_range| free_range.start < range.start);
+        debug_assert!(index == 0 || self.free_ranges[index - 1].end <= range.start);
+        debug_assert!(
+            index == self.free_ranges.len() || range.end <= self.free_ranges[index].start
+        );
+
+        let joins_previous = index > 0 && self.free_ranges[index - 1].end == range.start;
+        let joins_next =
+            index < self.free_ranges.len() && self.free_ranges[index].start == range.end;
+        match (joins_previous, joins_next) {
+            (true, true) => {
+                self.free_ranges[index - 1].end = self.free_ranges[index].end;
+                self.free_ranges.remove(index);
+            }
+            (true, false) => self.free_ranges[index - 1].end = range.end,
+            (false, true) => self.free_ranges[index].start = range.start,
+            (false, false) => self.free_ranges.insert(index, range),
+        }
+    }
+
+    // Adds the elements between the old and the new capacity as free space
+    pub fn grow(&mut self, capacity: usize) {
+        debug_assert!(capacity >= self.capacity);
+        let added = self.capacity..capacity;
+        self.capacity = capacity;
+        self.free(added);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeAllocator;
+
+    // Three neighbouring ranges of 10 out of a full allocator of 30
+    fn allocate_three() -> RangeAllocator {
+        let mut allocator = RangeAllocator::new(30);
+        for start in [0, 10, 20] {
+            assert_eq!(allocator.allocate(10), Some(start..start + 10));
+        }
+        allocator
+    }
+
+    #[test]
+    fn free_merges_with_previous_range() {
+        let mut allocator = allocate_three();
+        allocator.free(0..10);
+        allocator.free(10..20);
+        assert_eq!(allocator.free_ranges, vec![0..20]);
+        assert_eq!(allocator.used(), 10);
+    }
+
+    #[test]
+    fn free_merges_with_next_range() {
+        let mut allocator = allocate_three();
+        allocator.free(20..30);
+        allocator.free(10..20);
+        assert_eq!(allocator.free_ranges, vec![10..30]);
+        assert_eq!(allocator.used(), 10);
+    }
+
+    #[test]
+    fn free_merges_with_both_ranges() {
+        let mut allocator = allocate_three();
+        allocator.free(0..10);
+        allocator.free(20..30);
+        assert_eq!(allocator.free_ranges, vec![0..10, 20..30]);
+
+        allocator.free(10..20);
+        assert_eq!(allocator.free_ranges, vec![0..30]);
+        assert_eq!(allocator.used(), 0);
+        assert_eq!(allocator.allocate(30), Some(0..30));
+    }
+
+    #[test]
+    fn allocate_zero_takes_no_space() {
+        let mut allocator = allocate_three();
+        assert_eq!(allocator.allocate(0), Some(0..0));
+        allocator.free(0..0);
+        assert!(allocator.free_ranges.is_empty());
+        assert_eq!(allocator.used(), 30);
+    }
+
+    #[test]
+    fn grow_adds_free_space_at_the_end() {
+        let mut allocator = RangeAllocator::new(16);
+        assert_eq!(allocator.allocate(10), Some(0..10));
+        assert!(!allocator.can_allocate(10));
+        assert_eq!(allocator.allocate(10), None);
+
+        allocator.grow(32);
+        assert!(allocator.can_allocate(10));
+        assert_eq!(allocator.capacity(), 32);
+        assert_eq!(allocator.free_ranges, vec![10..32]);
+        assert_eq!(allocator.allocate(10), Some(10..20));
+        assert_eq!(allocator.used(), 20);
+    }
+
+    #[test]
+    fn grow_merges_with_free_space_at_the_end() {
+        let mut allocator = allocate_three();
+        allocator.free(20..30);
+        allocator.grow(40);
+        assert_eq!(allocator.free_ranges, vec![20..40]);
+    }
+}
@@ -0,0 +1,161 @@
+// Triangle reordering for the post-transform vertex cache, based on Tom Forsyth's
+// "Linear-Speed Vertex Cache Optimisation"
+
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+// Size of the FIFO cache used when measuring ACMR
+pub const SIMULATED_CACHE_SIZE: usize = 32;
+
+struct VertexData {
+    cache_position: Option<usize>,
+    score: f32,
+    triangles: Vec<usize>,
+    remaining_triangles: usize,
+}
+
+fn vertex_score(vertex: &VertexData) -> f32 {
+    if vertex.remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let mut score = match vertex.cache_position {
+        // The vertices of the last triangle get a fixed score, so the next triangle
+        // doesn't just reuse the same edge
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scaler = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+
+    // Favour vertices with few triangles left, so they don't get stranded
+    score += VALENCE_BOOST_SCALE * (vertex.remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+    score
+}
+
+// Returns the indices with the triangles reordered for better vertex cache reuse
+pub fn optimize(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return indices.to_vec();
+    }
+
+    let mut vertices: Vec<VertexData> = (0..vertex_count)
+        .map(|_| VertexData {
+            cache_position: None,
+            score: 0.0,
+            triangles: Vec::new(),
+            remaining_triangles: 0,
+        })
+        .collect();
+
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        for &vertex in corners {
+            vertices[vertex as usize].triangles.push(triangle);
+            vertices[vertex as usize].remaining_triangles += 1;
+        }
+    }
+
+    for vertex in vertices.iter_mut() {
+        vertex.score = vertex_score(vertex);
+    }
+
+    let triangle_score = |vertices: &[VertexData], triangle: usize| -> f32 {
+        indices[triangle * 3..triangle * 3 + 3]
+            .iter()
+            .map(|&v| vertices[v as usize].score)
+            .sum()
+    };
+
+    let mut triangle_added = vec![false; triangle_count];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut output: Vec<u32> = Vec::with_capacity(indices.len());
+
+    // Next triangle to consider when the cache has nothing to offer
+    let mut scan_position = 0;
+    let mut best_triangle = (0..triangle_count)
+        .max_by(|&a, &b| triangle_score(&vertices, a).total_cmp(&triangle_score(&vertices, b)));
+
+    while let Some(triangle) = best_triangle {
+        triangle_added[triangle] = true;
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        output.extend_from_slice(corners);
+
+        // Move the triangle's vertices to the front of the cache
+        for &vertex in corners.iter().rev() {
+            if let Some(position) = cache.iter().position(|&v| v == vertex) {
+                cache.remove(position);
+            }
+            cache.insert(0, vertex);
+
+            let data = &mut vertices[vertex as usize];
+            data.remaining_triangles -= 1;
+            if let Some(position) = data.triangles.iter().position(|&t| t == triangle) {
+                data.triangles.swap_remove(position);
+            }
+        }
+
+        for vertex in cache.drain(CACHE_SIZE.min(cache.len())..) {
+            vertices[vertex as usize].cache_position = None;
+            vertices[vertex as usize].score = vertex_score(&vertices[vertex as usize]);
+        }
+
+        for (position, &vertex) in cache.iter().enumerate() {
+            vertices[vertex as usize].cache_position = Some(position);
+            vertices[vertex as usize].score = vertex_score(&vertices[vertex as usize]);
+        }
+
+        // Only triangles touching the cache can have changed score
+        best_triangle = None;
+        let mut best_score = -1.0;
+        for &vertex in &cache {
+            for &t in &vertices[vertex as usize].triangles {
+                let score = triangle_score(&vertices, t);
+                if score > best_score {
+                    best_score = score;
+                    best_triangle = Some(t);
+                }
+            }
+        }
+
+        if best_triangle.is_none() {
+            while scan_position < triangle_count && triangle_added[scan_position] {
+                scan_position += 1;
+            }
+            if scan_position < triangle_count {
+                best_triangle = Some(scan_position);
+            }
+        }
+    }
+
+    output
+}
+
+// Average cache miss ratio: vertex shader invocations per triangle with a FIFO cache
+pub fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+
+    let mut cache: std::collections::VecDeque<u32> = std::collections::VecDeque::new();
+    let mut misses = 0;
+
+    for &index in indices {
+        if !cache.contains(&index) {
+            misses += 1;
+            cache.push_back(index);
+            if cache.len() > cache_size {
+                cache.pop_front();
+            }
+        }
+    }
+
+    misses as f32 / triangle_count as f32
+}
@@ -9,68 +9,76 @@ pub struct NoiseMap {
     data: Vec<Vec<f64>>,
 }
 
-impl NoiseMap {
-    pub fn new(settings: NoiseMapSettings) -> NoiseMap {
-        let NoiseMapSettings {
-            width,
-            height,
-            scale,
-            octaves,
-            persistence,
-            lacunarity,
-            seed,
-            offset_x,
-            offset_y,
-        } = settings;
-
-        let mut noise_map = vec![vec![0.0; height as usize]; width as usize];
+// Evaluates the layered noise of a noise map one point at a time
+pub struct NoiseSampler {
+    perlin: Perlin,
+    offsets: Vec<[f64; 2]>,
+    max_possible_height: f64,
+    settings: NoiseMapSettings,
+}
 
+impl NoiseSampler {
+    pub fn new(settings: NoiseMapSettings) -> NoiseSampler {
         let mut max_possible_height = 0.0;
         let mut amplitude = 1.0;
 
-        let perlin = Perlin::new(seed as u32);
-
-        let half_width = width as f64 / 2.0;
-        let half_height = height as f64 / 2.0;
-
-        let clamped_scale = scale.clamp(0.001, 100.0);
-        let mut r = StdRng::seed_from_u64(seed as u64);
+        let perlin = Perlin::new(settings.seed as u32);
+        let mut r = StdRng::seed_from_u64(settings.seed as u64);
 
         let mut offsets: Vec<[f64; 2]> = Vec::new();
 
-        for _octave in 0..octaves {
-            let r_offset_x = r.gen_range(-100000.0..100000.0) + offset_x;
-            let r_offset_y = r.gen_range(-100000.0..100000.0) + offset_y;
+        for _octave in 0..settings.octaves {
+            let r_offset_x = r.gen_range(-100000.0..100000.0) + settings.offset_x;
+            let r_offset_y = r.gen_range(-100000.0..100000.0) + settings.offset_y;
 
             offsets.push([r_offset_x, r_offset_y]);
 
             max_possible_height += amplitude;
-            amplitude *= persistence;
+            amplitude *= settings.persistence;
+        }
+
+        NoiseSampler {
+            perlin,
+            offsets,
+            max_possible_height,
+            settings,
+        }
+    }
+
+    // Normalized height of the pixel at (x, y) of the map
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        let half_width = self.settings.width as f64 / 2.0;
+        let half_height = self.settings.height as f64 / 2.0;
+        let clamped_scale = self.settings.scale.clamp(0.001, 100.0);
+
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut noise_height = 0.0;
+
+        for offset in &self.offsets {
+            let sample_x = (x - half_width + offset[0]) / clamped_scale * frequency;
+            let sample_y = (y - half_height - offset[1]) / clamped_scale * frequency;
+
+            let noise_value = self.perlin.get([sample_x, sample_y]);
+
+            noise_height += noise_value * amplitude;
+            amplitude *= self.settings.persistence;
+            frequency *= self.settings.lacunarity;
         }
 
-        for y in 0..height {
-            for x in 0..width {
-                amplitude = 1.0;
-                let mut frequency = 1.0;
-                let mut noise_height = 0.0;
-
-                for octave in 0..octaves {
-                    let sample_x = (x as f64 - half_width + offsets[octave as usize][0])
-                        / clamped_scale
-                        * frequency;
-                    let sample_y = (y as f64 - half_height - offsets[octave as usize][1])
-                        / clamped_scale
-                        * frequency;
-
-                    let noise_value = perlin.get([sample_x as f64, sample_y as f64]);
-
-                    noise_height += noise_value * amplitude;
-                    amplitude *= persistence;
-                    frequency *= lacunarity;
-                }
-
-                noise_map[x as usize][y as usize] =
-                    (noise_height + max_possible_height) / (max_possible_height * 2.0);
+        (noise_height + self.max_possible_height) / (self.max_possible_height * 2.0)
+    }
+}
+
+impl NoiseMap {
+    pub fn new(settings: NoiseMapSettings) -> NoiseMap {
+        let sampler = NoiseSampler::new(settings);
+
+        let mut noise_map = vec![vec![0.0; settings.height as usize]; settings.width as usize];
+
+        for y in 0..settings.height {
+            for x in 0..settings.width {
+                noise_map[x as usize][y as usize] = sampler.sample(x as f64, y as f64);
             }
         }
 
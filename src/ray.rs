@@ -0,0 +1,81 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: glm::Vec3,
+    // Always normalized, so distances along the ray are in world units
+    pub direction: glm::Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: glm::Vec3, direction: glm::Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: glm::normalize(&direction),
+        }
+    }
+
+    // The ray through a pixel of the window, cursor position in pixels from the top left
+    pub fn from_screen(
+        cursor_position: (f32, f32),
+        screen_size: (f32, f32),
+        view_projection_matrix: &glm::Mat4,
+    ) -> Ray {
+        let ndc_x = 2.0 * cursor_position.0 / screen_size.0 - 1.0;
+        let ndc_y = 1.0 - 2.0 * cursor_position.1 / screen_size.1;
+
+        let inverse = glm::inverse(view_projection_matrix);
+        let unproject = |ndc_z: f32| {
+            let point = inverse * glm::vec4(ndc_x, ndc_y, ndc_z, 1.0);
+            glm::vec3(point.x, point.y, point.z) / point.w
+        };
+
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        Ray::new(near, far - near)
+    }
+
+    pub fn at(&self, distance: f32) -> glm::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    // Möller–Trumbore, hits both sides of the triangle
+    pub fn intersect_triangle(&self, a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> Option<f32> {
+        let ab = b - a;
+        let ac = c - a;
+
+        let p = glm::cross(&self.direction, &ac);
+        let determinant = glm::dot(&ab, &p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+
+        let to_origin = self.origin - a;
+        let u = glm::dot(&to_origin, &p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = glm::cross(&to_origin, &ab);
+        let v = glm::dot(&self.direction, &q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = glm::dot(&ac, &q) * inverse_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+        Some(distance)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub position: glm::Vec3,
+    pub normal: glm::Vec3,
+    pub distance: f32,
+    pub chunk: (i32, i32),
+    // Index into the palette's materials, None when the chunk isn't loaded yet
+    pub material: Option<usize>,
+}
@@ -0,0 +1,143 @@
+use std::ptr;
+
+// An offscreen framebuffer with a color texture and a depth renderbuffer
+pub struct RenderTarget {
+    pub framebuffer_id: u32,
+    pub color_texture_id: u32,
+    depth_renderbuffer_id: u32,
+
+    pub width: i32,
+    pub height: i32,
+}
+
+impl RenderTarget {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut render_target = RenderTarget {
+            framebuffer_id: 0,
+            color_texture_id: 0,
+            depth_renderbuffer_id: 0,
+            width: 0,
+            height: 0,
+        };
+        render_target.resize(width, height);
+        render_target
+    }
+
+    // Recreates the attachments, does nothing if the size is unchanged
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return;
+        }
+        self.delete();
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut self.framebuffer_id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+
+            gl::GenTextures(1, &mut self.color_texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, self.color_texture_id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                self.color_texture_id,
+                0,
+            );
+
+            gl::GenRenderbuffers(1, &mut self.depth_renderbuffer_id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_renderbuffer_id);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                self.depth_renderbuffer_id,
+            );
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                println!("Render target of {}x{} is incomplete", width, height);
+            }
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        self.width = width;
+        self.height = height;
+    }
+
+    // Directs rendering into the target, covering all of it
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    // Goes back to the window's framebuffer
+    pub fn unbind(window_width: i32, window_height: i32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, window_width, window_height);
+        }
+    }
+
+    // Reads the color attachment back as rows of RGB pixels, top row first
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let row_size = self.width as usize * 3;
+        let mut pixels = vec![0u8; row_size * self.height as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer_id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                self.width,
+                self.height,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+
+        // OpenGL starts at the bottom row
+        let flipped: Vec<u8> = pixels.chunks(row_size).rev().flatten().copied().collect();
+        flipped
+    }
+
+    fn delete(&mut self) {
+        if self.framebuffer_id == 0 {
+            return;
+        }
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer_id);
+            gl::DeleteTextures(1, &self.color_texture_id);
+            gl::DeleteRenderbuffers(1, &self.depth_renderbuffer_id);
+        }
+        self.framebuffer_id = 0;
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.delete();
+    }
+}
@@ -0,0 +1,467 @@
+use std::path::PathBuf;
+
+use crate::{
+    camera::{frustum::Frustum, Camera},
+    chunk::ChunkContainer,
+    debug_view::{
+        debug_view_settings::{DebugView, DebugViewSettings},
+        DebugOverlay,
+    },
+    fog::fog_settings::{FogMode, FogSettings},
+    light::{
+        light_buffer::{LightBuffer, MAX_LIGHTS},
+        light_gizmo::LightGizmo,
+        light_settings::LightSettings,
+        light_source::{LightKind, LightSource},
+        scene_lights::SceneLights,
+        time_of_day::TimeOfDay,
+    },
+    material::{
+        material_buffer::{MaterialBuffer, MAX_MATERIALS},
+        TerrainColoring,
+    },
+    mesh::mesh_pool::MeshPool,
+    render_target::RenderTarget,
+    scenenode::SceneNode,
+    shader::{preprocessor::ShaderDefines, Shader},
+    shadow::{
+        shadow_settings::{ShadowSettings, MAX_CASCADES},
+        ShadowMap,
+    },
+    sky::{sky_settings::SkySettings, Sky},
+    water::{self, water_settings::WaterSettings, Water},
+    VIEW_DISTANCE,
+};
+
+const NEAR_PLANE: f32 = 1.0;
+
+// Where a frame ends up, the window's framebuffer or an offscreen target
+#[derive(Clone, Copy)]
+pub enum Output<'a> {
+    Window(i32, i32),
+    Image(&'a RenderTarget),
+}
+
+impl Output<'_> {
+    pub fn size(&self) -> (i32, i32) {
+        match self {
+            Output::Window(width, height) => (*width, *height),
+            Output::Image(target) => (target.width, target.height),
+        }
+    }
+
+    fn bind(&self) {
+        match self {
+            Output::Window(width, height) => RenderTarget::unbind(*width, *height),
+            Output::Image(target) => target.bind(),
+        }
+    }
+}
+
+// The settings edited in the debug view, lighting and water sections
+pub struct SceneSettings {
+    pub debug: DebugViewSettings,
+    pub time_of_day: TimeOfDay,
+    pub sky: SkySettings,
+    pub fog: FogSettings,
+    pub lights: SceneLights,
+    pub shadows: ShadowSettings,
+    pub water: WaterSettings,
+}
+
+impl SceneSettings {
+    pub fn new(hour: f32, sea_level: f32) -> Self {
+        SceneSettings {
+            debug: DebugViewSettings::new("Debug View".to_string()),
+            time_of_day: TimeOfDay::new("Time of Day".to_string(), hour),
+            sky: SkySettings::new("Sky".to_string()),
+            fog: FogSettings::new("Fog".to_string()),
+            lights: SceneLights::new(vec![LightSettings::point(
+                "Campfire".to_string(),
+                glm::vec3(0.0, 12.0, 0.0),
+            )]),
+            shadows: ShadowSettings::new("Shadows".to_string()),
+            water: WaterSettings::new("Water".to_string(), sea_level),
+        }
+    }
+}
+
+// The programs and buffers that draw the terrain, shared by the window and offscreen rendering
+pub struct Renderer {
+    // A permutation of the terrain program for each debug view
+    pub shape_shaders: Vec<Shader>,
+    pub material_buffer: MaterialBuffer,
+    light_buffer: LightBuffer,
+    light_gizmo: LightGizmo,
+    sky: Sky,
+    shadow_map: ShadowMap,
+    water: Water,
+    debug_overlay: DebugOverlay,
+}
+
+impl Renderer {
+    pub fn new(coloring: &TerrainColoring, settings: &SceneSettings) -> Result<Self, String> {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::Enable(gl::CULL_FACE);
+            gl::Disable(gl::MULTISAMPLE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+
+        let shape_shaders = DebugView::ALL
+            .iter()
+            .map(|view| {
+                Shader::from_files_with_defines(
+                    &["./shaders/shape.vert", "./shaders/shape.frag"],
+                    shape_defines(*view),
+                )
+            })
+            .collect::<Result<Vec<Shader>, String>>()?;
+
+        Ok(Renderer {
+            shape_shaders,
+            material_buffer: MaterialBuffer::new(coloring),
+            light_buffer: LightBuffer::new(&[]),
+            light_gizmo: LightGizmo::new(Shader::from_files(&[
+                "./shaders/light.vert",
+                "./shaders/light.frag",
+            ])?),
+            sky: Sky::new(Shader::from_files(&[
+                "./shaders/sky.vert",
+                "./shaders/sky.frag",
+            ])?),
+            shadow_map: ShadowMap::new(
+                Shader::from_files(&["./shaders/shadow.vert", "./shaders/shadow.frag"])?,
+                &settings.shadows,
+            ),
+            // Sized to half the output on the first frame
+            water: Water::new(
+                Shader::from_files_with_defines(
+                    &["./shaders/water.vert", "./shaders/water.frag"],
+                    fog_defines(ShaderDefines::new()),
+                )?,
+                1,
+                1,
+            ),
+            debug_overlay: DebugOverlay::new(
+                Shader::from_files(&["./shaders/debug_line.vert", "./shaders/debug_line.frag"])?,
+                Shader::from_files(&[
+                    "./shaders/normal_lines.vert",
+                    "./shaders/normal_lines.geom",
+                    "./shaders/debug_line.frag",
+                ])?,
+            ),
+        })
+    }
+
+    fn shaders_mut(&mut self) -> Vec<&mut Shader> {
+        let mut shaders: Vec<&mut Shader> = self.shape_shaders.iter_mut().collect();
+        shaders.extend([
+            &mut self.light_gizmo.shader,
+            &mut self.sky.shader,
+            &mut self.shadow_map.shader,
+            &mut self.water.shader,
+            &mut self.debug_overlay.line_shader,
+            &mut self.debug_overlay.normal_line_shader,
+        ]);
+        shaders
+    }
+
+    // Rebuilds the programs using any of the changed files
+    pub fn reload_shaders(&mut self, changed_paths: &[PathBuf]) {
+        for shader in self.shaders_mut() {
+            if !changed_paths.iter().any(|path| shader.uses(path)) {
+                continue;
+            }
+            match shader.reload() {
+                Ok(()) => println!("Reloaded {}", shader.paths.join(", ")),
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+
+    // Why the shaders that failed to reload did
+    pub fn shader_errors(&mut self) -> Vec<String> {
+        let mut errors: Vec<String> = self
+            .shaders_mut()
+            .into_iter()
+            .filter_map(|shader| shader.error.clone())
+            .collect();
+        // The permutations of a program usually fail the same way
+        errors.dedup();
+        errors
+    }
+
+    // The uniforms and attributes of every program, for checking them against the Rust side
+    pub fn describe_shaders(&mut self) -> String {
+        self.shaders_mut()
+            .into_iter()
+            .map(|shader| shader.describe_interface())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn projection_matrix(aspect_ratio: f32) -> glm::Mat4 {
+        glm::perspective(aspect_ratio, glm::half_pi(), NEAR_PLANE, VIEW_DISTANCE)
+    }
+
+    // Draws the sky, terrain, light gizmos and water as seen by the camera
+    pub fn render(
+        &mut self,
+        chunk_container: &mut ChunkContainer,
+        camera: &Camera,
+        settings: &SceneSettings,
+        time: f32,
+        output: Output,
+    ) {
+        let (width, height) = output.size();
+        let aspect_ratio = width as f32 / height as f32;
+        let shape_shader = &self.shape_shaders[settings.debug.view.shader_index() as usize];
+
+        let view_matrix = camera.get_look_at_matrix();
+        let projection_matrix = Renderer::projection_matrix(aspect_ratio);
+        let transformation_matrix = projection_matrix * view_matrix;
+
+        let fog_cutoff = settings
+            .fog
+            .opaque_distance(VIEW_DISTANCE)
+            .unwrap_or(f32::INFINITY);
+
+        self.water.resize_reflection(width, height);
+        self.water.update(
+            &chunk_container.visible_chunks(),
+            settings.water.sea_level,
+            &camera.position,
+            fog_cutoff,
+        );
+
+        // The sun goes first, followed by the lights placed in the scene
+        let sun_light = settings.time_of_day.get_sun_light();
+        let mut light_sources = vec![LightSource::from(&sun_light)];
+        light_sources.extend(settings.lights.get_light_sources());
+        self.light_buffer.upload(&light_sources);
+
+        self.material_buffer.bind();
+        self.light_buffer.bind();
+
+        // Render the depth of everything the sun shines on into the cascades
+        self.shadow_map.update_cascades(
+            &settings.shadows,
+            &view_matrix,
+            aspect_ratio,
+            glm::half_pi(),
+            NEAR_PLANE,
+            VIEW_DISTANCE,
+            &sun_light.direction,
+        );
+        if settings.shadows.enabled {
+            for (index, cascade) in self.shadow_map.cascades.iter().enumerate() {
+                let cascade_frustum = Frustum::from_matrix(&cascade.light_matrix);
+                let shadow_casters: Vec<SceneNode> = chunk_container.generate_scene(
+                    camera.position,
+                    &cascade_frustum,
+                    f32::INFINITY,
+                );
+                self.shadow_map
+                    .render_cascade(index, chunk_container.mesh_pool(), &shadow_casters);
+            }
+        }
+        self.shadow_map
+            .apply(shape_shader, &settings.shadows, &camera.front);
+
+        let sun_position = settings.time_of_day.sun_position();
+        self.sky.apply_aerial_perspective(
+            shape_shader,
+            &settings.sky,
+            &sun_position,
+            VIEW_DISTANCE,
+        );
+        settings.fog.apply(shape_shader, VIEW_DISTANCE);
+        self.sky.apply_aerial_perspective(
+            &self.water.shader,
+            &settings.sky,
+            &sun_position,
+            VIEW_DISTANCE,
+        );
+        settings.fog.apply(&self.water.shader, VIEW_DISTANCE);
+
+        // Render the terrain above the water mirrored in the water plane. Mirroring flips the
+        // winding of every triangle.
+        if settings.water.reflections {
+            let reflected_matrix =
+                transformation_matrix * water::reflection_matrix(settings.water.sea_level);
+            let reflected_frustum = Frustum::from_matrix(&reflected_matrix);
+            let reflected_scene: Vec<SceneNode> =
+                chunk_container.generate_scene(camera.position, &reflected_frustum, fog_cutoff);
+
+            self.water.reflection.bind();
+            unsafe {
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            self.sky.draw(
+                &settings.sky,
+                &reflected_matrix,
+                &camera.position,
+                &sun_position,
+            );
+            unsafe {
+                gl::FrontFace(gl::CW);
+                gl::Enable(gl::CLIP_DISTANCE0);
+                draw_scene(
+                    shape_shader,
+                    chunk_container.mesh_pool(),
+                    &reflected_scene,
+                    &reflected_matrix,
+                    &camera.position,
+                    &glm::vec4(0.0, 1.0, 0.0, -settings.water.sea_level),
+                );
+                gl::Disable(gl::CLIP_DISTANCE0);
+                gl::FrontFace(gl::CCW);
+            }
+        }
+
+        let frustum = camera.get_frustum(&projection_matrix);
+        let scene: Vec<SceneNode> =
+            chunk_container.generate_scene(camera.position, &frustum, fog_cutoff);
+
+        // Clear the color and depth buffers
+        output.bind();
+        let sky_color = settings.time_of_day.sky_color();
+        unsafe {
+            gl::ClearColor(sky_color.x, sky_color.y, sky_color.z, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        self.sky.draw(
+            &settings.sky,
+            &transformation_matrix,
+            &camera.position,
+            &sun_position,
+        );
+        let wireframe = settings.debug.view == DebugView::Wireframe;
+        unsafe {
+            if wireframe {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            }
+            draw_scene(
+                shape_shader,
+                chunk_container.mesh_pool(),
+                &scene,
+                &transformation_matrix,
+                &camera.position,
+                &glm::vec4(0.0, 0.0, 0.0, 0.0),
+            );
+            if wireframe {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            }
+        }
+        self.light_gizmo
+            .draw(&light_sources, &transformation_matrix);
+
+        self.water.draw(
+            &settings.water,
+            &transformation_matrix,
+            &frustum,
+            &camera.position,
+            &sun_light,
+            time,
+            (width as f32, height as f32),
+        );
+
+        // Drawn over the water, which doesn't write depth
+        if settings.debug.chunk_borders {
+            self.debug_overlay
+                .draw_chunk_borders(&chunk_container.load_states(), &transformation_matrix);
+        }
+        if settings.debug.normal_lines {
+            self.debug_overlay.draw_normal_lines(
+                chunk_container.mesh_pool(),
+                &scene,
+                &transformation_matrix,
+                &camera.position,
+                &settings.debug,
+            );
+        }
+    }
+
+    // Renders a frame offscreen and reads it back as rows of RGB pixels, top row first
+    pub fn render_to_image(
+        &mut self,
+        chunk_container: &mut ChunkContainer,
+        camera: &Camera,
+        settings: &SceneSettings,
+        time: f32,
+        size: (i32, i32),
+    ) -> Vec<u8> {
+        let capture = RenderTarget::new(size.0, size.1);
+        self.render(
+            chunk_container,
+            camera,
+            settings,
+            time,
+            Output::Image(&capture),
+        );
+        capture.read_pixels()
+    }
+}
+
+// The values shape.frag is built with, the debug view picks the permutation
+fn shape_defines(view: DebugView) -> ShaderDefines {
+    let mut defines = ShaderDefines::new()
+        .define("MAX_MATERIALS", MAX_MATERIALS)
+        .define("MAX_CASCADES", MAX_CASCADES)
+        .define("MAX_LIGHTS", MAX_LIGHTS)
+        .define("DEBUG_VIEW", view.shader_index());
+    let debug_views = [
+        ("DEBUG_VIEW_SHADED", DebugView::Shaded),
+        ("DEBUG_VIEW_WIREFRAME", DebugView::Wireframe),
+        ("DEBUG_VIEW_NORMALS", DebugView::Normals),
+        ("DEBUG_VIEW_LEVEL_OF_DETAIL", DebugView::LevelOfDetail),
+    ];
+    for (name, view) in debug_views {
+        defines = defines.define(name, view.shader_index());
+    }
+    let light_kinds = [
+        ("LIGHT_POINT", LightKind::Point),
+        ("LIGHT_SPOT", LightKind::Spot),
+        ("LIGHT_DIRECTIONAL", LightKind::Directional),
+    ];
+    for (name, kind) in light_kinds {
+        defines = defines.define(name, kind.shader_index());
+    }
+    fog_defines(defines)
+}
+
+// The FOG_* values fog.glsl compares the fog mode with
+fn fog_defines(mut defines: ShaderDefines) -> ShaderDefines {
+    let fog_modes = [
+        ("FOG_OFF", FogMode::Off),
+        ("FOG_LINEAR", FogMode::Linear),
+        ("FOG_EXPONENTIAL", FogMode::Exponential),
+        ("FOG_HEIGHT", FogMode::Height),
+    ];
+    for (name, mode) in fog_modes {
+        defines = defines.define(name, mode.shader_index());
+    }
+    defines
+}
+
+// Draws every node with one indirect call, the chunks read their model matrices from the pool
+unsafe fn draw_scene(
+    shader: &Shader,
+    mesh_pool: &mut MeshPool,
+    nodes: &[SceneNode],
+    view_projection_matrix: &glm::Mat4,
+    cam_pos: &glm::Vec3,
+    clip_plane: &glm::Vec4,
+) {
+    shader.activate();
+    shader.set_mat4("view_projection_matrix", view_projection_matrix);
+    shader.set_vec3("camera_position", cam_pos);
+    shader.set_vec4("clip_plane", clip_plane);
+
+    mesh_pool.draw(nodes);
+}
@@ -1,12 +1,30 @@
 extern crate nalgebra_glm as glm;
 
+use crate::mesh::mesh_pool::PoolRange;
+
 pub struct SceneNode {
-    pub vao_id: u32,
-    pub index_count: i32,
-    pub shader_program: u32,
+    // Where the mesh is in the mesh pool, None until it has been uploaded
+    pub mesh: Option<PoolRange>,
+    pub lod_index: usize,
 
     pub position: glm::Vec3,
     pub rotation: glm::Vec3,
     pub scale: glm::Vec3,
     pub reference_point: glm::Vec3,
 }
+
+impl SceneNode {
+    // Rotates and scales around the reference point, then moves the node into place
+    pub fn model_matrix(&self) -> glm::Mat4 {
+        let mut model_matrix = glm::translation(&-self.reference_point);
+
+        model_matrix = glm::rotation(self.rotation.x, &glm::vec3(1.0, 0.0, 0.0)) * model_matrix;
+        model_matrix = glm::rotation(self.rotation.y, &glm::vec3(0.0, 1.0, 0.0)) * model_matrix;
+        model_matrix = glm::rotation(self.rotation.z, &glm::vec3(0.0, 0.0, 1.0)) * model_matrix;
+
+        model_matrix = glm::scale(&model_matrix, &self.scale);
+
+        model_matrix = glm::translation(&self.reference_point) * model_matrix;
+        glm::translation(&self.position) * model_matrix
+    }
+}
@@ -0,0 +1,176 @@
+use imgui::Ui;
+use noise::{NoiseFn, Perlin};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrushKind {
+    Raise,
+    Lower,
+    Smooth,
+    Flatten,
+    Noise,
+    Terrace,
+}
+
+impl BrushKind {
+    const ALL: [BrushKind; 6] = [
+        BrushKind::Raise,
+        BrushKind::Lower,
+        BrushKind::Smooth,
+        BrushKind::Flatten,
+        BrushKind::Noise,
+        BrushKind::Terrace,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            BrushKind::Raise => "Raise",
+            BrushKind::Lower => "Lower",
+            BrushKind::Smooth => "Smooth",
+            BrushKind::Flatten => "Flatten",
+            BrushKind::Noise => "Noise",
+            BrushKind::Terrace => "Terrace",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Brush {
+    pub kind: BrushKind,
+    pub radius: f32,
+    // Height change per second for raise, lower and noise, blend rate per second for the others
+    pub strength: f32,
+    // Fraction of the radius over which the brush fades out towards its edge
+    pub falloff: f32,
+    pub terrace_height: f32,
+    pub noise_scale: f32,
+
+    // Height the flatten brush levels towards, taken where the stroke started
+    flatten_height: f32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            kind: BrushKind::Raise,
+            radius: 20.0,
+            strength: 5.0,
+            falloff: 0.5,
+            terrace_height: 2.0,
+            noise_scale: 8.0,
+            flatten_height: 0.0,
+        }
+    }
+}
+
+impl Brush {
+    pub fn begin_stroke(&mut self, height: f32) {
+        self.flatten_height = height;
+    }
+
+    // Brush weight at a distance from the center, as a fraction of the radius
+    fn weight(&self, distance: f32) -> f32 {
+        let inner = 1.0 - self.falloff;
+        if distance <= inner {
+            return 1.0;
+        }
+        let t = ((distance - inner) / self.falloff).clamp(0.0, 1.0);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    }
+
+    // Height changes of the vertices under the brush, for height_at giving the current
+    // height of the vertex at integer world coordinates
+    pub fn apply(
+        &self,
+        center: &glm::Vec3,
+        delta_time: f32,
+        mut height_at: impl FnMut(i32, i32) -> f32,
+    ) -> Vec<((i32, i32), f32)> {
+        let radius = self.radius.max(1.0);
+        let min_x = (center.x - radius).floor() as i32;
+        let max_x = (center.x + radius).ceil() as i32;
+        let min_z = (center.z - radius).floor() as i32;
+        let max_z = (center.z + radius).ceil() as i32;
+
+        // Read every height up front, with a one vertex margin for the smooth brush, so the
+        // changes don't depend on the order vertices are visited in
+        let width = (max_x - min_x + 3) as usize;
+        let mut heights = Vec::with_capacity(width * (max_z - min_z + 3) as usize);
+        for z in min_z - 1..=max_z + 1 {
+            for x in min_x - 1..=max_x + 1 {
+                heights.push(height_at(x, z));
+            }
+        }
+        let height =
+            |x: i32, z: i32| heights[(z - min_z + 1) as usize * width + (x - min_x + 1) as usize];
+
+        let noise = Perlin::new(0);
+        let mut changes = Vec::new();
+
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let distance = glm::distance(
+                    &glm::vec2(x as f32, z as f32),
+                    &glm::vec2(center.x, center.z),
+                );
+                if distance > radius {
+                    continue;
+                }
+
+                let amount = self.strength * self.weight(distance / radius) * delta_time;
+                let blend = amount.min(1.0);
+                let current = height(x, z);
+
+                let change = match self.kind {
+                    BrushKind::Raise => amount,
+                    BrushKind::Lower => -amount,
+                    BrushKind::Smooth => {
+                        let average = (height(x - 1, z)
+                            + height(x + 1, z)
+                            + height(x, z - 1)
+                            + height(x, z + 1))
+                            / 4.0;
+                        (average - current) * blend
+                    }
+                    BrushKind::Flatten => (self.flatten_height - current) * blend,
+                    BrushKind::Noise => {
+                        let scale = self.noise_scale.max(0.01) as f64;
+                        amount * noise.get([x as f64 / scale, z as f64 / scale]) as f32
+                    }
+                    BrushKind::Terrace => {
+                        let step = self.terrace_height.max(0.01);
+                        ((current / step).round() * step - current) * blend
+                    }
+                };
+
+                if change != 0.0 {
+                    changes.push(((x, z), change));
+                }
+            }
+        }
+        changes
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        // Three brushes per row
+        for (index, kind) in BrushKind::ALL.into_iter().enumerate() {
+            ui.radio_button(kind.name(), &mut self.kind, kind);
+            if index % 3 != 2 {
+                ui.same_line();
+            }
+        }
+
+        ui.slider("Radius##brush", 1.0, 100.0, &mut self.radius);
+        ui.slider("Strength##brush", 0.0, 50.0, &mut self.strength);
+        ui.slider("Falloff##brush", 0.0, 1.0, &mut self.falloff);
+
+        match self.kind {
+            BrushKind::Terrace => {
+                ui.slider("Terrace height", 0.1, 10.0, &mut self.terrace_height);
+            }
+            BrushKind::Noise => {
+                ui.slider("Noise scale", 1.0, 50.0, &mut self.noise_scale);
+            }
+            _ => {}
+        }
+    }
+}
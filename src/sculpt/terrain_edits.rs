@@ -0,0 +1,187 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::CHUNK_PIXEL_SIZE;
+
+const EDITS_MAGIC: &[u8; 4] = b"TEDT";
+const EDITS_FORMAT_VERSION: u32 = 1;
+
+// Vertices in one chunk's delta layer, matching the chunk's height field
+const LAYER_SIZE: usize = ((CHUNK_PIXEL_SIZE + 1) * (CHUNK_PIXEL_SIZE + 1)) as usize;
+
+// Hand made height changes on top of the procedural terrain, stored as one delta per vertex
+// for every chunk that has been edited
+#[derive(Clone, Default)]
+pub struct TerrainEdits {
+    layers: HashMap<(i32, i32), Arc<Vec<f32>>>,
+    // Chunks edited since their mesh was last requested
+    dirty_chunks: HashSet<(i32, i32)>,
+    // Bounds on every delta, only ever widened while editing
+    lowest_delta: f32,
+    highest_delta: f32,
+}
+
+impl TerrainEdits {
+    // Shared with chunk generation threads, which only read the layer
+    pub fn layer(&self, chunk: (i32, i32)) -> Option<Arc<Vec<f32>>> {
+        self.layers.get(&chunk).cloned()
+    }
+
+    // The layers of a chunk and its eight neighbours, for generating the chunk on another
+    // thread
+    pub fn around(&self, chunk: (i32, i32)) -> TerrainEdits {
+        let mut edits = TerrainEdits::default();
+        for z_offset in -1..=1 {
+            for x_offset in -1..=1 {
+                let neighbour = (chunk.0 + x_offset, chunk.1 + z_offset);
+                if let Some(layer) = self.layer(neighbour) {
+                    edits.layers.insert(neighbour, layer);
+                }
+            }
+        }
+        edits
+    }
+
+    pub fn delta(&self, chunk: (i32, i32), grid_x: i32, grid_z: i32) -> f32 {
+        self.layers.get(&chunk).map_or(0.0, |layer| {
+            layer[grid_z as usize * (CHUNK_PIXEL_SIZE + 1) as usize + grid_x as usize]
+        })
+    }
+
+    pub fn add(&mut self, chunk: (i32, i32), grid_x: i32, grid_z: i32, change: f32) {
+        let layer = self
+            .layers
+            .entry(chunk)
+            .or_insert_with(|| Arc::new(vec![0.0; LAYER_SIZE]));
+        let delta = &mut Arc::make_mut(layer)
+            [grid_z as usize * (CHUNK_PIXEL_SIZE + 1) as usize + grid_x as usize];
+        *delta += change;
+
+        self.lowest_delta = self.lowest_delta.min(*delta);
+        self.highest_delta = self.highest_delta.max(*delta);
+        self.dirty_chunks.insert(chunk);
+    }
+
+    // Lowest and highest delta, so height bounds of the terrain can account for the edits
+    pub fn delta_range(&self) -> (f32, f32) {
+        (self.lowest_delta, self.highest_delta)
+    }
+
+    pub fn dirty_chunks(&self) -> Vec<(i32, i32)> {
+        self.dirty_chunks.iter().copied().collect()
+    }
+
+    // Meshes the chunk again without changing its edits, for edits next to it that reach into
+    // its ambient occlusion
+    pub fn mark_dirty(&mut self, chunk: (i32, i32)) {
+        self.dirty_chunks.insert(chunk);
+    }
+
+    pub fn mark_clean(&mut self, chunk: (i32, i32)) {
+        self.dirty_chunks.remove(&chunk);
+    }
+
+    pub fn mark_all_clean(&mut self) {
+        self.dirty_chunks.clear();
+    }
+
+    // Drops every edit, leaving the edited chunks dirty so they return to the procedural terrain
+    pub fn clear(&mut self) {
+        self.dirty_chunks.extend(self.layers.keys().copied());
+        self.layers.clear();
+        self.lowest_delta = 0.0;
+        self.highest_delta = 0.0;
+    }
+
+    // A missing file means nothing has been edited yet
+    pub fn load(path: &str) -> Result<TerrainEdits, String> {
+        if !Path::new(path).exists() {
+            return Ok(TerrainEdits::default());
+        }
+
+        let file = fs::File::open(path).map_err(|e| format!("Could not open {}: {}", path, e))?;
+        let mut reader = BufReader::new(file);
+        let error = |e: std::io::Error| format!("Could not read {}: {}", path, e);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(error)?;
+        if &magic != EDITS_MAGIC {
+            return Err(format!("{} is not a terrain edits file", path));
+        }
+
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word).map_err(error)?;
+        let version = u32::from_le_bytes(word);
+        if version != EDITS_FORMAT_VERSION {
+            return Err(format!(
+                "{} has format version {}, expected {}",
+                path, version, EDITS_FORMAT_VERSION
+            ));
+        }
+
+        reader.read_exact(&mut word).map_err(error)?;
+        let layer_count = u32::from_le_bytes(word);
+
+        let mut edits = TerrainEdits::default();
+        let mut layer_bytes = vec![0u8; LAYER_SIZE * 4];
+        for _ in 0..layer_count {
+            reader.read_exact(&mut word).map_err(error)?;
+            let chunk_x = i32::from_le_bytes(word);
+            reader.read_exact(&mut word).map_err(error)?;
+            let chunk_z = i32::from_le_bytes(word);
+
+            reader.read_exact(&mut layer_bytes).map_err(error)?;
+            let layer: Vec<f32> = layer_bytes
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            for delta in &layer {
+                edits.lowest_delta = edits.lowest_delta.min(*delta);
+                edits.highest_delta = edits.highest_delta.max(*delta);
+            }
+            edits.layers.insert((chunk_x, chunk_z), Arc::new(layer));
+        }
+
+        Ok(edits)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(directory) = Path::new(path).parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Could not create {}: {}", directory.display(), e))?;
+        }
+
+        let file =
+            fs::File::create(path).map_err(|e| format!("Could not create {}: {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend(EDITS_MAGIC);
+        bytes.extend(EDITS_FORMAT_VERSION.to_le_bytes());
+        bytes.extend((self.layers.len() as u32).to_le_bytes());
+        writer
+            .write_all(&bytes)
+            .map_err(|e| format!("Could not write {}: {}", path, e))?;
+
+        for (chunk, layer) in &self.layers {
+            bytes.clear();
+            bytes.extend(chunk.0.to_le_bytes());
+            bytes.extend(chunk.1.to_le_bytes());
+            layer
+                .iter()
+                .for_each(|delta| bytes.extend(delta.to_le_bytes()));
+            writer
+                .write_all(&bytes)
+                .map_err(|e| format!("Could not write {}: {}", path, e))?;
+        }
+
+        writer
+            .flush()
+            .map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+}
@@ -0,0 +1,251 @@
+pub mod preprocessor;
+pub mod shader_watcher;
+pub mod uniforms;
+
+use gl;
+use std::{cell::RefCell, collections::HashMap, ffi::CString, path::Path, ptr, str};
+
+use self::{preprocessor::ShaderDefines, uniforms::UniformDeclarations};
+
+pub struct Shader {
+    pub program_id: u32,
+    // The files the program is built from, in the order they are attached
+    pub paths: Vec<String>,
+    // The files pulled in with #include, a change to them needs a rebuild as well
+    pub included_paths: Vec<String>,
+    pub defines: ShaderDefines,
+    // Why the last reload failed, the previous program stays in use meanwhile
+    pub error: Option<String>,
+    // Looked up by name on first use, -1 for uniforms the program doesn't have
+    uniform_locations: RefCell<HashMap<String, i32>>,
+    declarations: UniformDeclarations,
+}
+
+pub struct ShaderBuilder {
+    program_id: u32,
+    shaders: Vec<u32>,
+    paths: Vec<String>,
+    included_paths: Vec<String>,
+    defines: ShaderDefines,
+    declarations: UniformDeclarations,
+}
+
+#[allow(dead_code)]
+pub enum ShaderType {
+    Vertex,
+    Fragment,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Compute,
+}
+
+impl Shader {
+    // Compiles and links the files into a new program
+    pub fn from_files(paths: &[&str]) -> Result<Shader, String> {
+        Shader::from_files_with_defines(paths, ShaderDefines::new())
+    }
+
+    // Compiles and links the files with the defines added to each of them
+    pub fn from_files_with_defines(
+        paths: &[&str],
+        defines: ShaderDefines,
+    ) -> Result<Shader, String> {
+        unsafe {
+            paths
+                .iter()
+                .try_fold(ShaderBuilder::with_defines(defines), |builder, path| {
+                    builder.attach_file(path)
+                })?
+                .link()
+        }
+    }
+
+    pub unsafe fn activate(&self) {
+        gl::UseProgram(self.program_id);
+    }
+
+    pub fn uses(&self, path: &Path) -> bool {
+        self.paths
+            .iter()
+            .chain(&self.included_paths)
+            .any(|own_path| Path::new(own_path) == path)
+    }
+
+    // Builds the program again from its files. The old program is only replaced once the new
+    // one links, so a typo in a shader doesn't leave the scene without it.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let paths: Vec<&str> = self.paths.iter().map(String::as_str).collect();
+        let result = Shader::from_files_with_defines(&paths, self.defines.clone()).map(|shader| {
+            unsafe {
+                gl::DeleteProgram(self.program_id);
+            }
+            self.program_id = shader.program_id;
+            self.included_paths = shader.included_paths;
+            self.uniform_locations = shader.uniform_locations;
+            self.declarations = shader.declarations;
+        });
+        self.error = result.clone().err();
+        result
+    }
+}
+
+impl Into<gl::types::GLenum> for ShaderType {
+    fn into(self) -> gl::types::GLenum {
+        match self {
+            ShaderType::Vertex => gl::VERTEX_SHADER,
+            ShaderType::Fragment => gl::FRAGMENT_SHADER,
+            ShaderType::TessellationControl => gl::TESS_CONTROL_SHADER,
+            ShaderType::TessellationEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderType::Geometry => gl::GEOMETRY_SHADER,
+            ShaderType::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+}
+
+impl ShaderType {
+    fn from_ext(ext: &std::ffi::OsStr) -> Result<ShaderType, String> {
+        match ext.to_str().unwrap_or_default() {
+            "vert" => Ok(ShaderType::Vertex),
+            "frag" => Ok(ShaderType::Fragment),
+            "tcs" => Ok(ShaderType::TessellationControl),
+            "tes" => Ok(ShaderType::TessellationEvaluation),
+            "geom" => Ok(ShaderType::Geometry),
+            "comp" => Ok(ShaderType::Compute),
+            e => Err(format!("Unknown shader extension '{}'", e)),
+        }
+    }
+}
+
+impl ShaderBuilder {
+    pub unsafe fn new() -> ShaderBuilder {
+        ShaderBuilder::with_defines(ShaderDefines::new())
+    }
+
+    unsafe fn with_defines(defines: ShaderDefines) -> ShaderBuilder {
+        ShaderBuilder {
+            program_id: gl::CreateProgram(),
+            shaders: vec![],
+            paths: vec![],
+            included_paths: vec![],
+            defines,
+            declarations: UniformDeclarations::default(),
+        }
+    }
+
+    pub unsafe fn attach_file(mut self, shader_path: &str) -> Result<ShaderBuilder, String> {
+        let path = Path::new(shader_path);
+        let extension = path
+            .extension()
+            .ok_or_else(|| format!("{} has no extension to tell its type", shader_path))?;
+        let shader_type =
+            ShaderType::from_ext(extension).map_err(|e| format!("{}: {}", shader_path, e))?;
+        let source = preprocessor::preprocess(shader_path, &self.defines)?;
+
+        self.paths.push(shader_path.to_string());
+        for included_path in source.files.iter().skip(1) {
+            if !self.included_paths.contains(included_path) {
+                self.included_paths.push(included_path.clone());
+            }
+        }
+        self.declarations.add(&source.code);
+        self.compile_shader(&source.code, shader_type)
+            .map_err(|e| format!("{} failed to compile:\n{}", shader_path, source.map_log(&e)))
+    }
+
+    pub unsafe fn compile_shader(
+        mut self,
+        shader_src: &str,
+        shader_type: ShaderType,
+    ) -> Result<ShaderBuilder, String> {
+        let shader = gl::CreateShader(shader_type.into());
+        // Kept before checking, so the builder deletes it either way
+        self.shaders.push(shader);
+
+        let c_str_shader = CString::new(shader_src.as_bytes())
+            .map_err(|_| "The source contains a nul character".to_string())?;
+        gl::ShaderSource(shader, 1, &c_str_shader.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut success = i32::from(gl::FALSE);
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+        if success != i32::from(gl::TRUE) {
+            return Err(ShaderBuilder::info_log(
+                shader,
+                gl::GetShaderiv,
+                gl::GetShaderInfoLog,
+            ));
+        }
+        Ok(self)
+    }
+
+    // The whole log of a shader or program, however long it is
+    unsafe fn info_log(
+        object_id: u32,
+        get_parameter: unsafe fn(u32, gl::types::GLenum, *mut i32),
+        get_log: unsafe fn(u32, i32, *mut i32, *mut gl::types::GLchar),
+    ) -> String {
+        let mut length = 0;
+        get_parameter(object_id, gl::INFO_LOG_LENGTH, &mut length);
+        if length <= 0 {
+            return "No info log".to_string();
+        }
+
+        let mut info_log = vec![0u8; length as usize];
+        let mut written = 0;
+        get_log(
+            object_id,
+            length,
+            &mut written,
+            info_log.as_mut_ptr() as *mut gl::types::GLchar,
+        );
+        info_log.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&info_log).trim_end().to_string()
+    }
+
+    pub unsafe fn link(mut self) -> Result<Shader, String> {
+        for &shader in &self.shaders {
+            gl::AttachShader(self.program_id, shader);
+        }
+        gl::LinkProgram(self.program_id);
+
+        let mut success = i32::from(gl::FALSE);
+        gl::GetProgramiv(self.program_id, gl::LINK_STATUS, &mut success);
+        if success != i32::from(gl::TRUE) {
+            let log =
+                ShaderBuilder::info_log(self.program_id, gl::GetProgramiv, gl::GetProgramInfoLog);
+            return Err(format!(
+                "{} failed to link:\n{}",
+                self.paths.join(", "),
+                log
+            ));
+        }
+
+        // The program keeps what it needs from the shaders
+        let program_id = self.program_id;
+        self.program_id = 0;
+        Ok(Shader {
+            program_id,
+            paths: std::mem::take(&mut self.paths),
+            included_paths: std::mem::take(&mut self.included_paths),
+            defines: std::mem::take(&mut self.defines),
+            error: None,
+            uniform_locations: RefCell::new(HashMap::new()),
+            declarations: std::mem::take(&mut self.declarations),
+        })
+    }
+}
+
+// Cleans up after a failed compile or link, and deletes the shader objects after a link
+impl Drop for ShaderBuilder {
+    fn drop(&mut self) {
+        unsafe {
+            for &shader in &self.shaders {
+                gl::DeleteShader(shader);
+            }
+            if self.program_id != 0 {
+                gl::DeleteProgram(self.program_id);
+            }
+        }
+    }
+}
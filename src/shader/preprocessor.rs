@@ -0,0 +1,304 @@
+use std::{fs, path::Path};
+
+// Values handed to the GLSL source as #defines. Building the same files with different values
+// gives permutations of a program.
+#[derive(Clone, Default)]
+pub struct ShaderDefines {
+    values: Vec<(String, String)>,
+}
+
+impl ShaderDefines {
+    pub fn new() -> Self {
+        ShaderDefines::default()
+    }
+
+    pub fn define(mut self, name: &str, value: impl ToString) -> Self {
+        self.values.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+// A shader with its includes pasted in and the defines added, ready to compile
+pub struct PreprocessedSource {
+    pub code: String,
+    // The files the code came from
+    pub files: Vec<String>,
+    // The file index and 1 based line number each line of the code came from, the defines
+    // have none
+    line_origins: Vec<Option<(usize, usize)>>,
+}
+
+impl PreprocessedSource {
+    // Points the messages of a compiler log at the original files. Drivers don't agree on
+    // honouring the source numbers of #line, so the code has none and the lines are looked up
+    // instead.
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.map_log_line(line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // Mesa starts messages with "0:12(5):", Nvidia with "0(12) :" and AMD with "ERROR: 0:12:"
+    fn map_log_line(&self, line: &str) -> String {
+        let start = ["ERROR: ", "WARNING: "]
+            .iter()
+            .find(|prefix| line.starts_with(*prefix))
+            .map_or(0, |prefix| prefix.len());
+        let source_end = start + count_digits(&line[start..]);
+        let separator = match line[source_end..].chars().next() {
+            Some(separator @ (':' | '(')) if source_end != start => separator,
+            _ => return line.to_string(),
+        };
+        let line_start = source_end + separator.len_utf8();
+        let line_end = line_start + count_digits(&line[line_start..]);
+
+        let origin = line[line_start..line_end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| self.line_origins.get(number.checked_sub(1)?))
+            .copied()
+            .flatten();
+        match origin {
+            Some((file_index, file_line)) => format!(
+                "{}{}{}{}{}",
+                &line[..start],
+                self.files[file_index],
+                separator,
+                file_line,
+                &line[line_end..]
+            ),
+            None => line.to_string(),
+        }
+    }
+}
+
+fn count_digits(text: &str) -> usize {
+    text.chars().take_while(char::is_ascii_digit).count()
+}
+
+// Reads a shader, resolving #include "file" relative to the including file and adding the
+// defines after the #version line
+pub fn preprocess(path: &str, defines: &ShaderDefines) -> Result<PreprocessedSource, String> {
+    let source = read_source(path)?;
+    let version_line = source
+        .lines()
+        .position(|line| line.trim_start().starts_with("#version"))
+        .ok_or_else(|| format!("{} has no #version line", path))?;
+
+    // Nothing but comments may come before the version, so the defines go right after it
+    let mut preprocessor = Preprocessor {
+        code: String::new(),
+        files: vec![path.to_string()],
+        line_origins: Vec::new(),
+    };
+    for (line_index, line) in source.lines().enumerate().take(version_line + 1) {
+        preprocessor.push_line(line, Some((0, line_index + 1)));
+    }
+    for (name, value) in &defines.values {
+        preprocessor.push_line(&format!("#define {} {}", name, value), None);
+    }
+    preprocessor.append_file(0, &source, version_line + 1)?;
+
+    Ok(PreprocessedSource {
+        code: preprocessor.code,
+        files: preprocessor.files,
+        line_origins: preprocessor.line_origins,
+    })
+}
+
+struct Preprocessor {
+    code: String,
+    files: Vec<String>,
+    line_origins: Vec<Option<(usize, usize)>>,
+}
+
+impl Preprocessor {
+    fn push_line(&mut self, line: &str, origin: Option<(usize, usize)>) {
+        self.code.push_str(line);
+        self.code.push('\n');
+        self.line_origins.push(origin);
+    }
+
+    // Appends the lines of a file from the 0 based first_line on, replacing its includes with
+    // the included files. A file is only included once, which also stops include cycles.
+    fn append_file(
+        &mut self,
+        file_index: usize,
+        source: &str,
+        first_line: usize,
+    ) -> Result<(), String> {
+        let path = self.files[file_index].clone();
+        for (line_index, line) in source.lines().enumerate().skip(first_line) {
+            let included = match parse_include(line) {
+                Some(included) => {
+                    included.map_err(|e| format!("{}:{}: {}", path, line_index + 1, e))?
+                }
+                None => {
+                    self.push_line(line, Some((file_index, line_index + 1)));
+                    continue;
+                }
+            };
+
+            let included_path = Path::new(&path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(included)
+                .to_string_lossy()
+                .to_string();
+            if !self.files.contains(&included_path) {
+                let included_source = read_source(&included_path)
+                    .map_err(|e| format!("{}:{}: {}", path, line_index + 1, e))?;
+                self.files.push(included_path);
+                self.append_file(self.files.len() - 1, &included_source, 0)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_source(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+// The file named by an #include line, None for any other line
+fn parse_include(line: &str) -> Option<Result<&str, String>> {
+    let argument = line.trim().strip_prefix("#include")?.trim();
+    Some(
+        argument
+            .strip_prefix('"')
+            .and_then(|argument| argument.strip_suffix('"'))
+            .ok_or_else(|| format!("Expected #include \"file\", found {}", line.trim())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{preprocess, ShaderDefines};
+
+    // Writes the files into a new directory, returning the path of the first
+    fn write_files(name: &str, files: &[(&str, &str)]) -> (PathBuf, String) {
+        let directory = env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (file_name, source) in files {
+            fs::write(directory.join(file_name), source).unwrap();
+        }
+        let main_path = directory.join(files[0].0).to_string_lossy().into_owned();
+        (directory, main_path)
+    }
+
+    // main.frag includes a.glsl, which includes b.glsl, which includes both of them again
+    fn include_cycle(name: &str) -> (PathBuf, String) {
+        write_files(
+            name,
+            &[
+                (
+                    "main.frag",
+                    "#version 450 core\n#include \"a.glsl\"\nvoid main() {}\n",
+                ),
+                ("a.glsl", "#include \"b.glsl\"\nfloat a;\n"),
+                (
+                    "b.glsl",
+                    "#include \"a.glsl\"\n#include \"main.frag\"\nfloat b;\n",
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn include_cycle_includes_each_file_once() {
+        let (directory, main_path) = include_cycle("preprocessor_cycle");
+        let source = preprocess(&main_path, &ShaderDefines::new().define("X", 1)).unwrap();
+
+        assert_eq!(
+            source.code,
+            "#version 450 core\n#define X 1\nfloat b;\nfloat a;\nvoid main() {}\n"
+        );
+        let file_names: Vec<&str> = source
+            .files
+            .iter()
+            .map(|file| file.rsplit(['/', '\\']).next().unwrap())
+            .collect();
+        assert_eq!(file_names, ["main.frag", "a.glsl", "b.glsl"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn include_errors_name_the_including_line() {
+        let (directory, main_path) = write_files(
+            "preprocessor_errors",
+            &[
+                (
+                    "main.frag",
+                    "#version 450 core\n#include \"missing.glsl\"\n",
+                ),
+                ("quoted.frag", "#version 450 core\n\n#include <a.glsl>\n"),
+            ],
+        );
+        let error = preprocess(&main_path, &ShaderDefines::new()).err().unwrap();
+        assert!(error.starts_with(&format!("{}:2: Failed to read", main_path)));
+
+        let quoted_path = directory.join("quoted.frag").to_string_lossy().into_owned();
+        let error = preprocess(&quoted_path, &ShaderDefines::new())
+            .err()
+            .unwrap();
+        assert!(error.starts_with(&format!("{}:3: Expected #include", quoted_path)));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn log_lines_point_at_the_original_files() {
+        let (directory, main_path) = include_cycle("preprocessor_log");
+        let source = preprocess(&main_path, &ShaderDefines::new().define("X", 1)).unwrap();
+        let a_path = &source.files[1];
+        let b_path = &source.files[2];
+
+        // Line 3 of the code is line 3 of b.glsl, line 4 is line 2 of a.glsl and line 5 is
+        // line 3 of main.frag
+        let mesa = "0:3(7): error: `c' undeclared";
+        let nvidia = "0(4) : error C1008: undefined variable \"c\"";
+        let amd = "ERROR: 0:5: 'c' : undeclared identifier";
+        assert_eq!(
+            source.map_log(&[mesa, nvidia, amd].join("\n")),
+            [
+                format!("{}:3(7): error: `c' undeclared", b_path),
+                format!("{}(2) : error C1008: undefined variable \"c\"", a_path),
+                format!("ERROR: {}:3: 'c' : undeclared identifier", main_path),
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            source.map_log("WARNING: 0:4: implicit cast"),
+            format!("WARNING: {}:2: implicit cast", a_path)
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn log_lines_without_an_origin_are_kept() {
+        let (directory, main_path) = include_cycle("preprocessor_unmapped");
+        let source = preprocess(&main_path, &ShaderDefines::new().define("X", 1)).unwrap();
+
+        for line in [
+            // The injected define, a line past the end and line 0
+            "0:2(9): error: syntax error",
+            "0:99(1): error: syntax error",
+            "0(0) : error",
+            // Not a message, a multi-byte character after the digits and a missing source
+            "Compilation failed",
+            "0é1: error",
+            "ERROR: :3: error",
+            "",
+        ] {
+            assert_eq!(source.map_log(line), line);
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+// Notices shader files in a directory being saved, so the programs using them can be rebuilt
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    modified_times: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new(directory: &str) -> Self {
+        let mut watcher = ShaderWatcher {
+            directory: PathBuf::from(directory),
+            modified_times: HashMap::new(),
+        };
+        watcher.poll();
+        watcher
+    }
+
+    // The files that were added or modified since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut changed = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let modified = match fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            if self.modified_times.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+}
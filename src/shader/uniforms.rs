@@ -0,0 +1,427 @@
+use std::{collections::HashMap, ffi::CString};
+
+use super::Shader;
+
+// A value the shaders declare as a GLSL struct, each field is set as name.field
+pub trait UniformStruct {
+    fn set_uniforms(&self, shader: &Shader, name: &str);
+}
+
+// The uniforms the sources of a program declare outside of blocks, and the fields of the
+// structs they define. The #if branches are read too, so uniforms a permutation's defines
+// leave out still count as declared.
+#[derive(Clone, Default)]
+pub struct UniformDeclarations {
+    // Type of each uniform
+    uniforms: HashMap<String, String>,
+    // Type of each field of each struct
+    structs: HashMap<String, HashMap<String, String>>,
+}
+
+impl UniformDeclarations {
+    pub fn add(&mut self, source: &str) {
+        let code: String = strip_comments(source)
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        let mut rest = code.as_str();
+        let mut outside_structs = String::new();
+        while let Some((before, name, body, after)) = next_struct(rest) {
+            outside_structs.push_str(before);
+            outside_structs.push(';');
+            let fields = self.structs.entry(name.to_string()).or_default();
+            for field in body.split(';') {
+                fields.extend(declared_names(field));
+            }
+            rest = after;
+        }
+        outside_structs.push_str(rest);
+
+        for statement in outside_structs.split(';') {
+            // Only what follows the last brace belongs to the statement, the rest are blocks
+            // and function bodies
+            let statement = statement.rsplit(['{', '}']).next().unwrap_or(statement);
+            let mut words = statement.split_whitespace();
+            if words.any(|word| word == "uniform") {
+                let declaration = words.collect::<Vec<&str>>().join(" ");
+                self.uniforms.extend(declared_names(&declaration));
+            }
+        }
+    }
+
+    // Whether a name like sun.direction or light_matrices[1] is a uniform or a field of one
+    pub fn declares(&self, name: &str) -> bool {
+        let mut parts = name
+            .split('.')
+            .map(|part| part.split('[').next().unwrap_or(part));
+        let mut type_name = match parts.next().and_then(|part| self.uniforms.get(part)) {
+            Some(type_name) => type_name,
+            None => return false,
+        };
+        for part in parts {
+            type_name = match self
+                .structs
+                .get(type_name)
+                .and_then(|fields| fields.get(part))
+            {
+                Some(type_name) => type_name,
+                None => return false,
+            };
+        }
+        true
+    }
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut code = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("//").into_iter().chain(rest.find("/*")).min() {
+        code.push_str(&rest[..start]);
+        rest = if rest[start..].starts_with("//") {
+            rest[start..]
+                .find('\n')
+                .map_or("", |end| &rest[start + end..])
+        } else {
+            code.push(' ');
+            rest[start..]
+                .find("*/")
+                .map_or("", |end| &rest[start + end + 2..])
+        };
+    }
+    code.push_str(rest);
+    code
+}
+
+// The text before the next struct definition, its name, its body and the text after it
+fn next_struct(code: &str) -> Option<(&str, &str, &str, &str)> {
+    let start = code
+        .match_indices("struct")
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+            !code[..index].ends_with(is_identifier)
+                && code[index + 6..].starts_with(char::is_whitespace)
+        })?;
+    let open = start + code[start..].find('{')?;
+    let close = open + code[open..].find('}')?;
+    let name = code[start + 6..open].trim();
+    Some((
+        &code[..start],
+        name,
+        &code[open + 1..close],
+        &code[close + 1..],
+    ))
+}
+
+// The names and type of a declaration like "vec3 a, b[2]", with qualifiers before the type
+fn declared_names(declaration: &str) -> Vec<(String, String)> {
+    let mut names = declaration
+        .split(',')
+        .map(|name| name.split(['=', '[']).next().unwrap_or(name));
+    let first: Vec<&str> = names
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    let (type_name, first_name) = match first.as_slice() {
+        [.., type_name, name] => (*type_name, *name),
+        _ => return Vec::new(),
+    };
+    std::iter::once(first_name)
+        .chain(names.map(str::trim))
+        .filter(|name| !name.is_empty())
+        .map(|name| (name.to_string(), type_name.to_string()))
+        .collect()
+}
+
+// A uniform or vertex attribute the linked program actually uses
+pub struct ActiveVariable {
+    pub name: String,
+    // -1 for the members of uniform blocks, which have no location
+    pub location: i32,
+    pub type_name: String,
+    // Element count of arrays, 1 otherwise
+    pub size: i32,
+}
+
+// The setters don't need the program to be active
+impl Shader {
+    // Where a uniform lives, asking the driver only the first time. Names the program doesn't
+    // have are reported once, setting them does nothing.
+    pub fn uniform_location(&self, name: &str) -> i32 {
+        if let Some(&location) = self.uniform_locations.borrow().get(name) {
+            return location;
+        }
+
+        let name_cstr = CString::new(name).expect("CString::new failed");
+        let location = unsafe { gl::GetUniformLocation(self.program_id, name_cstr.as_ptr()) };
+        // The compiler drops uniforms a permutation doesn't use, only undeclared names are
+        // mistakes
+        if location < 0 && !self.declarations.declares(name) {
+            println!(
+                "{} has no uniform named {}, setting it does nothing",
+                self.paths.join(", "),
+                name
+            );
+        }
+        self.uniform_locations
+            .borrow_mut()
+            .insert(name.to_string(), location);
+        location
+    }
+
+    pub fn set_i32(&self, name: &str, value: i32) {
+        unsafe {
+            gl::ProgramUniform1i(self.program_id, self.uniform_location(name), value);
+        }
+    }
+
+    pub fn set_f32(&self, name: &str, value: f32) {
+        unsafe {
+            gl::ProgramUniform1f(self.program_id, self.uniform_location(name), value);
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: &glm::Vec2) {
+        unsafe {
+            gl::ProgramUniform2fv(
+                self.program_id,
+                self.uniform_location(name),
+                1,
+                value.as_ptr(),
+            );
+        }
+    }
+
+    pub fn set_vec3(&self, name: &str, value: &glm::Vec3) {
+        unsafe {
+            gl::ProgramUniform3fv(
+                self.program_id,
+                self.uniform_location(name),
+                1,
+                value.as_ptr(),
+            );
+        }
+    }
+
+    pub fn set_vec4(&self, name: &str, value: &glm::Vec4) {
+        unsafe {
+            gl::ProgramUniform4fv(
+                self.program_id,
+                self.uniform_location(name),
+                1,
+                value.as_ptr(),
+            );
+        }
+    }
+
+    // Matrices are uploaded transposed, the shaders multiply with the vector on the left
+    pub fn set_mat4(&self, name: &str, value: &glm::Mat4) {
+        self.set_mat4_array(name, std::slice::from_ref(value));
+    }
+
+    pub fn set_mat4_array(&self, name: &str, values: &[glm::Mat4]) {
+        unsafe {
+            gl::ProgramUniformMatrix4fv(
+                self.program_id,
+                self.uniform_location(name),
+                values.len() as i32,
+                gl::TRUE,
+                values.as_ptr() as *const f32,
+            );
+        }
+    }
+
+    pub fn set_struct(&self, name: &str, value: &impl UniformStruct) {
+        value.set_uniforms(self, name);
+    }
+
+    pub fn active_uniforms(&self) -> Vec<ActiveVariable> {
+        unsafe {
+            self.active_variables(
+                gl::ACTIVE_UNIFORMS,
+                gl::ACTIVE_UNIFORM_MAX_LENGTH,
+                gl::GetActiveUniform,
+                gl::GetUniformLocation,
+            )
+        }
+    }
+
+    pub fn active_attributes(&self) -> Vec<ActiveVariable> {
+        unsafe {
+            self.active_variables(
+                gl::ACTIVE_ATTRIBUTES,
+                gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+                gl::GetActiveAttrib,
+                gl::GetAttribLocation,
+            )
+        }
+    }
+
+    // The interface of the program as the driver sees it, one variable per line
+    pub fn describe_interface(&self) -> String {
+        let mut description = format!("{}\n", self.paths.join(", "));
+        for (kind, variables) in [
+            ("attribute", self.active_attributes()),
+            ("uniform", self.active_uniforms()),
+        ] {
+            // The members of uniform blocks are set through buffers and left out
+            for variable in variables.iter().filter(|variable| variable.location >= 0) {
+                let size = match variable.size {
+                    1 => String::new(),
+                    size => format!("[{}]", size),
+                };
+                description += &format!(
+                    "  {} {:>3} {} {}{}\n",
+                    kind, variable.location, variable.type_name, variable.name, size
+                );
+            }
+        }
+        description
+    }
+
+    unsafe fn active_variables(
+        &self,
+        count_parameter: gl::types::GLenum,
+        max_length_parameter: gl::types::GLenum,
+        get_active: unsafe fn(u32, u32, i32, *mut i32, *mut i32, *mut u32, *mut gl::types::GLchar),
+        get_location: unsafe fn(u32, *const gl::types::GLchar) -> i32,
+    ) -> Vec<ActiveVariable> {
+        let mut count = 0;
+        let mut max_length = 0;
+        gl::GetProgramiv(self.program_id, count_parameter, &mut count);
+        gl::GetProgramiv(self.program_id, max_length_parameter, &mut max_length);
+
+        (0..count.max(0) as u32)
+            .map(|index| {
+                let mut name = vec![0u8; max_length.max(1) as usize];
+                let mut length = 0;
+                let mut size = 0;
+                let mut variable_type = 0;
+                get_active(
+                    self.program_id,
+                    index,
+                    max_length,
+                    &mut length,
+                    &mut size,
+                    &mut variable_type,
+                    name.as_mut_ptr() as *mut gl::types::GLchar,
+                );
+                name.truncate(length.max(0) as usize);
+                let name = String::from_utf8_lossy(&name).to_string();
+                let name_cstr = CString::new(name.as_str()).expect("CString::new failed");
+
+                ActiveVariable {
+                    location: get_location(self.program_id, name_cstr.as_ptr()),
+                    // Arrays are reported by their first element
+                    name: name.trim_end_matches("[0]").to_string(),
+                    type_name: type_name(variable_type),
+                    size,
+                }
+            })
+            .collect()
+    }
+}
+
+fn type_name(variable_type: gl::types::GLenum) -> String {
+    match variable_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        other => return format!("0x{:04X}", other),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UniformDeclarations;
+
+    const SOURCE: &str = "#version 450 core
+struct DirectionalLight {
+    vec3 direction; // the way the light travels
+    vec3 ambient, diffuse;
+};
+struct Cascade { mat4 matrix; float splits[4]; };
+
+layout(std140, binding=0) uniform MaterialPalette {
+    vec4 palette_settings;
+};
+
+// uniform float commented_out;
+/* uniform float
+   also_commented_out; */
+uniform DirectionalLight sun;
+layout(binding=1) uniform sampler1D height_gradient;
+uniform highp float fog_start = 0.0, fog_end;
+uniform Cascade cascades[MAX_CASCADES];
+#if DEBUG_VIEW == DEBUG_VIEW_NORMALS
+uniform vec3 normal_tint;
+#endif
+
+void main()
+{
+    vec3 color = sun.ambient;
+}
+";
+
+    fn declarations() -> UniformDeclarations {
+        let mut declarations = UniformDeclarations::default();
+        declarations.add(SOURCE);
+        declarations
+    }
+
+    #[test]
+    fn declared_uniforms_and_fields() {
+        let declarations = declarations();
+        for name in [
+            "sun",
+            "sun.direction",
+            "sun.diffuse",
+            "height_gradient",
+            "fog_start",
+            "fog_end",
+            "cascades[2].matrix",
+            "cascades[0].splits[3]",
+            // Left out by the permutation, still declared
+            "normal_tint",
+        ] {
+            assert!(declarations.declares(name), "{} should be declared", name);
+        }
+    }
+
+    #[test]
+    fn undeclared_names() {
+        let declarations = declarations();
+        for name in [
+            // Words of the source that aren't uniforms or their fields
+            "sun.color",
+            "color",
+            "direction",
+            "main",
+            "sun.direction.x",
+            "commented_out",
+            "also_commented_out",
+            // Members of uniform blocks are set through buffers
+            "palette_settings",
+            "MaterialPalette",
+        ] {
+            assert!(
+                !declarations.declares(name),
+                "{} should be undeclared",
+                name
+            );
+        }
+    }
+}
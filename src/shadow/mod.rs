@@ -0,0 +1,267 @@
+pub mod shadow_settings;
+
+use std::ptr;
+
+use crate::{mesh::mesh_pool::MeshPool, scenenode::SceneNode, shader::Shader};
+
+use self::shadow_settings::{ShadowSettings, MAX_CASCADES};
+
+const SHADOW_TEXTURE_UNIT: u32 = 4;
+
+// World units behind a cascade that still cast shadows into it, for mountains outside the view
+const SHADOW_CASTER_MARGIN: f32 = 600.0;
+
+// Slope scaled and constant depth offsets applied while rendering the depth maps
+const POLYGON_OFFSET_FACTOR: f32 = 2.0;
+const POLYGON_OFFSET_UNITS: f32 = 4.0;
+
+// The part of the camera frustum one depth map covers, as seen from the light
+#[derive(Clone, Copy)]
+pub struct Cascade {
+    pub light_matrix: glm::Mat4,
+    // View depth where the next cascade takes over
+    pub split_depth: f32,
+}
+
+// Cascaded shadow maps for a directional light, stored as layers of one depth texture array
+pub struct ShadowMap {
+    pub shader: Shader,
+    framebuffer_id: u32,
+    depth_texture_id: u32,
+    resolution: i32,
+    layers: i32,
+
+    pub cascades: Vec<Cascade>,
+}
+
+impl ShadowMap {
+    pub fn new(shader: Shader, settings: &ShadowSettings) -> Self {
+        let mut framebuffer_id: u32 = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer_id);
+        }
+        let mut shadow_map = ShadowMap {
+            shader,
+            framebuffer_id,
+            depth_texture_id: 0,
+            resolution: 0,
+            layers: 0,
+            cascades: Vec::new(),
+        };
+        shadow_map.resize(settings.resolution, settings.cascade_count);
+        shadow_map
+    }
+
+    // Recreates the depth texture, does nothing if the size is unchanged
+    fn resize(&mut self, resolution: i32, layers: i32) {
+        if resolution == self.resolution && layers == self.layers {
+            return;
+        }
+
+        unsafe {
+            if self.depth_texture_id != 0 {
+                gl::DeleteTextures(1, &self.depth_texture_id);
+            }
+            gl::GenTextures(1, &mut self.depth_texture_id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.depth_texture_id);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT32F as i32,
+                resolution,
+                resolution,
+                layers,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                ptr::null(),
+            );
+            // Linear filtering with depth comparison gives a bilinear 2x2 PCF for free
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MAG_FILTER,
+                gl::LINEAR as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_COMPARE_MODE,
+                gl::COMPARE_REF_TO_TEXTURE as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_COMPARE_FUNC,
+                gl::LEQUAL as i32,
+            );
+            // Anything outside a depth map is lit
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_S,
+                gl::CLAMP_TO_BORDER as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_T,
+                gl::CLAMP_TO_BORDER as i32,
+            );
+            let border = [1.0f32, 1.0, 1.0, 1.0];
+            gl::TexParameterfv(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_BORDER_COLOR,
+                border.as_ptr(),
+            );
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+
+        self.resolution = resolution;
+        self.layers = layers;
+    }
+
+    // Splits the camera frustum between near and far into cascades and fits a light space
+    // projection around each of them
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_cascades(
+        &mut self,
+        settings: &ShadowSettings,
+        view_matrix: &glm::Mat4,
+        aspect_ratio: f32,
+        field_of_view: f32,
+        near: f32,
+        far: f32,
+        light_direction: &glm::Vec3,
+    ) {
+        self.resize(settings.resolution, settings.cascade_count);
+
+        let light_direction = glm::normalize(light_direction);
+        let up = if light_direction.y.abs() > 0.99 {
+            glm::vec3(0.0, 0.0, 1.0)
+        } else {
+            glm::vec3(0.0, 1.0, 0.0)
+        };
+
+        self.cascades.clear();
+        let count = settings.cascade_count.clamp(1, MAX_CASCADES);
+        let mut cascade_near = near;
+
+        for cascade in 1..=count {
+            // Blend of uniform and logarithmic splits, logarithmic keeps the near cascades small
+            let fraction = cascade as f32 / count as f32;
+            let uniform_split = near + (far - near) * fraction;
+            let logarithmic_split = near * (far / near).powf(fraction);
+            let cascade_far = settings.split_lambda * logarithmic_split
+                + (1.0 - settings.split_lambda) * uniform_split;
+
+            let projection =
+                glm::perspective(aspect_ratio, field_of_view, cascade_near, cascade_far);
+            let inverse = glm::inverse(&(projection * view_matrix));
+
+            let mut corners: Vec<glm::Vec3> = Vec::with_capacity(8);
+            for x in [-1.0, 1.0] {
+                for y in [-1.0, 1.0] {
+                    for z in [-1.0, 1.0] {
+                        let corner = inverse * glm::vec4(x, y, z, 1.0);
+                        corners.push(corner.xyz() / corner.w);
+                    }
+                }
+            }
+
+            // A bounding sphere keeps the projection the same size while the camera turns,
+            // so the shadow edges don't shimmer
+            let center = corners.iter().sum::<glm::Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| glm::distance(corner, &center))
+                .fold(0.0, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let light_view = glm::look_at(&(center - light_direction * radius), &center, &up);
+            let mut light_projection = glm::ortho(
+                -radius,
+                radius,
+                -radius,
+                radius,
+                -SHADOW_CASTER_MARGIN,
+                radius * 2.0,
+            );
+
+            // Move the projection in whole texels, for the same reason
+            let half_resolution = settings.resolution as f32 / 2.0;
+            let origin = light_projection * light_view * glm::vec4(0.0, 0.0, 0.0, 1.0);
+            let texel_origin = glm::vec2(origin.x, origin.y) * half_resolution;
+            let offset = (glm::vec2(texel_origin.x.round(), texel_origin.y.round()) - texel_origin)
+                / half_resolution;
+            light_projection[(0, 3)] += offset.x;
+            light_projection[(1, 3)] += offset.y;
+
+            self.cascades.push(Cascade {
+                light_matrix: light_projection * light_view,
+                split_depth: cascade_far,
+            });
+            cascade_near = cascade_far;
+        }
+    }
+
+    // Renders the depth of the nodes into one cascade's layer
+    pub fn render_cascade(&self, cascade: usize, mesh_pool: &mut MeshPool, nodes: &[SceneNode]) {
+        let light_matrix = self.cascades[cascade].light_matrix;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+            gl::FramebufferTextureLayer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                self.depth_texture_id,
+                0,
+                cascade as i32,
+            );
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            gl::Viewport(0, 0, self.resolution, self.resolution);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+            // The terrain is an open surface, so both sides have to cast
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(POLYGON_OFFSET_FACTOR, POLYGON_OFFSET_UNITS);
+
+            self.shader.activate();
+            self.shader.set_mat4("light_matrix", &light_matrix);
+            mesh_pool.draw(nodes);
+
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+            gl::Enable(gl::CULL_FACE);
+        }
+    }
+
+    // Hands the cascades to a program that samples them, no cascades turns shadows off
+    pub fn apply(&self, shader: &Shader, settings: &ShadowSettings, camera_forward: &glm::Vec3) {
+        let cascade_count = if settings.enabled {
+            self.cascades.len() as i32
+        } else {
+            0
+        };
+
+        let mut light_matrices = [glm::Mat4::identity(); MAX_CASCADES as usize];
+        let mut split_depths = glm::vec4(0.0, 0.0, 0.0, 0.0);
+        for (index, cascade) in self.cascades.iter().enumerate() {
+            light_matrices[index] = cascade.light_matrix;
+            split_depths[index] = cascade.split_depth;
+        }
+
+        shader.set_i32("cascade_count", cascade_count);
+        shader.set_mat4_array("light_matrices", &light_matrices);
+        shader.set_vec4("cascade_splits", &split_depths);
+        shader.set_f32("shadow_bias", settings.bias);
+        shader.set_vec3("camera_forward", camera_forward);
+        shader.set_i32("pcf_radius", settings.pcf_radius);
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.depth_texture_id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use imgui::{CollapsingHeader, Ui};
+
+// Defined as MAX_CASCADES in shape.frag
+pub const MAX_CASCADES: i32 = 4;
+
+const RESOLUTIONS: [i32; 4] = [512, 1024, 2048, 4096];
+
+#[derive(Clone, PartialEq)]
+pub struct ShadowSettings {
+    pub name: String,
+    pub enabled: bool,
+    pub cascade_count: i32,
+    // Width and height of every cascade's depth map
+    pub resolution: i32,
+    // World units the lookup moves along the surface normal, against shadow acne
+    pub bias: f32,
+    // Texels sampled on each side of the lookup, 0 samples only the hardware filtered texel
+    pub pcf_radius: i32,
+    // Blend between uniform (0) and logarithmic (1) cascade splits
+    pub split_lambda: f32,
+}
+
+impl ShadowSettings {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            enabled: true,
+            cascade_count: 3,
+            resolution: 2048,
+            bias: 0.3,
+            pcf_radius: 1,
+            split_lambda: 0.75,
+        }
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.checkbox("Shadows", &mut self.enabled);
+            ui.slider("Cascades", 1, MAX_CASCADES, &mut self.cascade_count);
+            ui.text("Resolution");
+            for (index, resolution) in RESOLUTIONS.iter().enumerate() {
+                if index > 0 {
+                    ui.same_line();
+                }
+                ui.radio_button(resolution.to_string(), &mut self.resolution, *resolution);
+            }
+            ui.slider("Shadow bias", 0.0, 2.0, &mut self.bias);
+            ui.slider("PCF radius", 0, 3, &mut self.pcf_radius);
+            ui.slider("Split lambda", 0.0, 1.0, &mut self.split_lambda);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+pub mod sky_settings;
+
+use crate::shader::Shader;
+
+use self::sky_settings::SkySettings;
+
+// Sky colour when the sun is far below the horizon, the scattering alone would be black
+const NIGHT_SKY_COLOR: [f32; 3] = [0.035, 0.046, 0.078];
+
+// Single scattering atmosphere drawn behind everything else
+pub struct Sky {
+    pub shader: Shader,
+    // Empty, the full screen triangle is built from gl_VertexID
+    vao_id: u32,
+}
+
+impl Sky {
+    pub fn new(shader: Shader) -> Self {
+        let mut vao_id: u32 = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao_id);
+        }
+        Sky { shader, vao_id }
+    }
+
+    // Sets the uniforms of atmosphere.glsl in a program that evaluates the scattering
+    pub fn apply(&self, shader: &Shader, settings: &SkySettings, sun_position: &glm::Vec3) {
+        shader.set_vec3("sun_position", sun_position);
+        shader.set_vec3("rayleigh_coefficients", &settings.rayleigh_coefficients());
+        shader.set_f32("mie_coefficient", settings.mie_coefficient());
+        shader.set_f32("rayleigh_scale_height", settings.rayleigh_scale_height);
+        shader.set_f32("mie_scale_height", settings.mie_scale_height);
+        shader.set_f32("mie_anisotropy", settings.mie_anisotropy);
+        shader.set_f32("sun_intensity", settings.sun_intensity);
+        shader.set_f32("metres_per_unit", settings.metres_per_unit);
+        shader.set_f32("exposure", settings.exposure);
+        shader.set_vec3("night_sky_color", &glm::Vec3::from(NIGHT_SKY_COLOR));
+    }
+
+    // The atmosphere uniforms of the terrain program, which fades the terrain into the sky
+    pub fn apply_aerial_perspective(
+        &self,
+        shader: &Shader,
+        settings: &SkySettings,
+        sun_position: &glm::Vec3,
+        view_distance: f32,
+    ) {
+        self.apply(shader, settings, sun_position);
+        shader.set_f32("view_distance", view_distance);
+        shader.set_i32("aerial_perspective", settings.aerial_perspective as i32);
+    }
+
+    // Fills the background, call before drawing anything else into the target
+    pub fn draw(
+        &self,
+        settings: &SkySettings,
+        view_projection_matrix: &glm::Mat4,
+        camera_position: &glm::Vec3,
+        sun_position: &glm::Vec3,
+    ) {
+        self.apply(&self.shader, settings, sun_position);
+        self.shader.set_mat4(
+            "inverse_view_projection",
+            &glm::inverse(view_projection_matrix),
+        );
+        self.shader.set_vec3("camera_position", camera_position);
+
+        unsafe {
+            self.shader.activate();
+
+            // Mirrored passes flip the winding of the triangle
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            gl::BindVertexArray(self.vao_id);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::CULL_FACE);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use imgui::{CollapsingHeader, Ui};
+
+// Rayleigh scattering of air at sea level per metre, for red, green and blue light
+const RAYLEIGH_COEFFICIENTS: [f32; 3] = [5.5e-6, 13.0e-6, 22.4e-6];
+// Mie scattering of haze at sea level per metre, the same for every colour
+const MIE_COEFFICIENT: f32 = 21e-6;
+
+#[derive(Clone, PartialEq)]
+pub struct SkySettings {
+    pub name: String,
+    // Scale the sea level coefficients, more scattering gives a thicker atmosphere
+    pub rayleigh_strength: f32,
+    pub mie_strength: f32,
+    // Heights in metres over which the density of air and haze falls to 1/e
+    pub rayleigh_scale_height: f32,
+    pub mie_scale_height: f32,
+    // How much haze scatters forward, towards 1 the glow around the sun gets tighter
+    pub mie_anisotropy: f32,
+    pub sun_intensity: f32,
+    pub exposure: f32,
+    // Metres covered by one world unit, sets how much air lies between the camera and the
+    // terrain
+    pub metres_per_unit: f32,
+    pub aerial_perspective: bool,
+}
+
+impl SkySettings {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            rayleigh_strength: 1.0,
+            mie_strength: 1.0,
+            rayleigh_scale_height: 8000.0,
+            mie_scale_height: 1200.0,
+            mie_anisotropy: 0.76,
+            sun_intensity: 22.0,
+            exposure: 1.0,
+            metres_per_unit: 30.0,
+            aerial_perspective: true,
+        }
+    }
+
+    pub fn rayleigh_coefficients(&self) -> glm::Vec3 {
+        glm::Vec3::from(RAYLEIGH_COEFFICIENTS) * self.rayleigh_strength
+    }
+
+    pub fn mie_coefficient(&self) -> f32 {
+        MIE_COEFFICIENT * self.mie_strength
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.slider("Rayleigh strength", 0.0, 5.0, &mut self.rayleigh_strength);
+            ui.slider("Mie strength", 0.0, 10.0, &mut self.mie_strength);
+            ui.slider(
+                "Rayleigh scale height",
+                1000.0,
+                20000.0,
+                &mut self.rayleigh_scale_height,
+            );
+            ui.slider(
+                "Mie scale height",
+                100.0,
+                5000.0,
+                &mut self.mie_scale_height,
+            );
+            ui.slider("Mie anisotropy", 0.0, 0.99, &mut self.mie_anisotropy);
+            ui.slider("Sun intensity", 1.0, 50.0, &mut self.sun_intensity);
+            ui.slider("Exposure", 0.1, 5.0, &mut self.exposure);
+            ui.slider("Metres per unit", 1.0, 200.0, &mut self.metres_per_unit);
+            ui.checkbox("Aerial perspective", &mut self.aerial_perspective);
+        }
+    }
+}
@@ -24,6 +24,39 @@ pub fn offset<T>(n: u32) -> *const c_void {
     (n * mem::size_of::<T>() as u32) as *const T as *const c_void
 }
 
+// Convert an f32 to the bits of an IEEE 754 half precision float, rounding to nearest
+// Example usage:  f32_to_f16(0.5)
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Subnormal half floats
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        let full_mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let round = (full_mantissa >> (shift - 1)) & 1;
+        return sign | ((full_mantissa >> shift) + round) as u16;
+    }
+
+    let round = (mantissa >> 12) & 1;
+    (sign | ((half_exponent as u16) << 10) | (mantissa >> 13) as u16) + round as u16
+}
+
 // Get a null pointer (equivalent to an offset of 0)
 // ptr::null()
 
@@ -1,13 +1,27 @@
-use crate::material::Material;
-
 #[derive(Clone, Copy)]
 pub struct Vertex {
     pub position: glm::Vec3,
-    pub material: Material,
+    pub normal: glm::Vec3,
+    pub material_index: u32,
+    pub height: f32,
+    // Share of the sky visible from the vertex
+    pub occlusion: f32,
 }
 
 impl Vertex {
-    pub fn new(position: glm::Vec3, material: Material) -> Vertex {
-        Vertex { position, material }
+    pub fn new(
+        position: glm::Vec3,
+        normal: glm::Vec3,
+        material_index: u32,
+        height: f32,
+        occlusion: f32,
+    ) -> Vertex {
+        Vertex {
+            position,
+            normal,
+            material_index,
+            height,
+            occlusion,
+        }
     }
 }
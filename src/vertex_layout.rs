@@ -0,0 +1,199 @@
+use crate::utils;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttributeType {
+    Float,
+    HalfFloat,
+    UnsignedInt,
+    UnsignedShort,
+    // Three signed 10 bit components and a 2 bit w, read as a vec4
+    Int2101010Rev,
+}
+
+impl AttributeType {
+    fn gl_type(self) -> gl::types::GLenum {
+        match self {
+            AttributeType::Float => gl::FLOAT,
+            AttributeType::HalfFloat => gl::HALF_FLOAT,
+            AttributeType::UnsignedInt => gl::UNSIGNED_INT,
+            AttributeType::UnsignedShort => gl::UNSIGNED_SHORT,
+            AttributeType::Int2101010Rev => gl::INT_2_10_10_10_REV,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        matches!(
+            self,
+            AttributeType::UnsignedInt | AttributeType::UnsignedShort
+        )
+    }
+
+    fn byte_size(self, components: usize) -> usize {
+        match self {
+            AttributeType::Float | AttributeType::UnsignedInt => 4 * components,
+            AttributeType::HalfFloat | AttributeType::UnsignedShort => 2 * components,
+            AttributeType::Int2101010Rev => 4,
+        }
+    }
+
+    // Attributes start at a multiple of this, so GL never reads a component across a word
+    fn alignment(self) -> usize {
+        match self {
+            AttributeType::HalfFloat | AttributeType::UnsignedShort => 2,
+            _ => 4,
+        }
+    }
+
+    fn encode(self, values: &[f32], out: &mut Vec<u8>) {
+        match self {
+            AttributeType::Float => values.iter().for_each(|v| out.extend(v.to_le_bytes())),
+            AttributeType::HalfFloat => values
+                .iter()
+                .for_each(|v| out.extend(utils::f32_to_f16(*v).to_le_bytes())),
+            AttributeType::UnsignedInt => values
+                .iter()
+                .for_each(|v| out.extend((*v as u32).to_le_bytes())),
+            AttributeType::UnsignedShort => values
+                .iter()
+                .for_each(|v| out.extend((*v as u16).to_le_bytes())),
+            AttributeType::Int2101010Rev => {
+                let pack = |v: f32| ((v.clamp(-1.0, 1.0) * 511.0).round() as i32 as u32) & 0x3ff;
+                let x = values.first().copied().unwrap_or(0.0);
+                let y = values.get(1).copied().unwrap_or(0.0);
+                let z = values.get(2).copied().unwrap_or(0.0);
+                let packed = pack(x) | (pack(y) << 10) | (pack(z) << 20);
+                out.extend(packed.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    pub location: u32,
+    pub components: usize,
+    pub attribute_type: AttributeType,
+    pub normalized: bool,
+}
+
+impl VertexAttribute {
+    pub fn new(
+        name: &'static str,
+        location: u32,
+        components: usize,
+        attribute_type: AttributeType,
+        normalized: bool,
+    ) -> Self {
+        Self {
+            name,
+            location,
+            components,
+            attribute_type,
+            normalized,
+        }
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.attribute_type.byte_size(self.components)
+    }
+}
+
+// Vertices start on a word boundary, drivers read attributes that aren't 4 byte aligned slowly
+const VERTEX_ALIGNMENT: usize = 4;
+
+// Describes how the attributes of one vertex are laid out in an interleaved buffer. 16 bit
+// attributes next to each other share a word, the stride is padded to whole words.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(attributes: Vec<VertexAttribute>) -> Self {
+        Self { attributes }
+    }
+
+    pub fn stride(&self) -> usize {
+        let end = self.attributes.last().map_or(0, |last| {
+            self.offset_of(self.attributes.len() - 1) + last.byte_size()
+        });
+        end.next_multiple_of(VERTEX_ALIGNMENT)
+    }
+
+    pub fn offset_of(&self, index: usize) -> usize {
+        let mut offset: usize = 0;
+        for attribute in &self.attributes[..index] {
+            offset = offset.next_multiple_of(attribute.attribute_type.alignment());
+            offset += attribute.byte_size();
+        }
+        offset.next_multiple_of(self.attributes[index].attribute_type.alignment())
+    }
+
+    // Appends one vertex, given one slice of values per attribute in layout order
+    pub fn push_vertex(&self, data: &mut Vec<u8>, values: &[&[f32]]) {
+        debug_assert_eq!(values.len(), self.attributes.len());
+        let start = data.len();
+        for (index, (attribute, value)) in self.attributes.iter().zip(values).enumerate() {
+            data.resize(start + self.offset_of(index), 0);
+            attribute.attribute_type.encode(value, data);
+        }
+        data.resize(start + self.stride(), 0);
+    }
+
+    // Reads back an integer attribute of one vertex
+    pub fn read_integer(&self, data: &[u8], vertex: usize, attribute: usize) -> u32 {
+        let start = vertex * self.stride() + self.offset_of(attribute);
+        match self.attributes[attribute].attribute_type {
+            AttributeType::UnsignedInt => {
+                u32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+            }
+            AttributeType::UnsignedShort => {
+                u16::from_le_bytes(data[start..start + 2].try_into().unwrap()) as u32
+            }
+            attribute_type => panic!("{:?} is not an integer attribute", attribute_type),
+        }
+    }
+
+    // Sets up the attribute pointers for the vertex buffer currently bound to GL_ARRAY_BUFFER
+    pub fn apply(&self) {
+        unsafe {
+            let stride = self.stride() as i32;
+
+            for (index, attribute) in self.attributes.iter().enumerate() {
+                let offset = utils::offset::<u8>(self.offset_of(index) as u32);
+                let gl_type = attribute.attribute_type.gl_type();
+                let components = if attribute.attribute_type == AttributeType::Int2101010Rev {
+                    4
+                } else {
+                    attribute.components as i32
+                };
+
+                if attribute.attribute_type.is_integer() {
+                    gl::VertexAttribIPointer(
+                        attribute.location,
+                        components,
+                        gl_type,
+                        stride,
+                        offset,
+                    );
+                } else {
+                    let normalized = if attribute.normalized {
+                        gl::TRUE
+                    } else {
+                        gl::FALSE
+                    };
+                    gl::VertexAttribPointer(
+                        attribute.location,
+                        components,
+                        gl_type,
+                        normalized,
+                        stride,
+                        offset,
+                    );
+                }
+                gl::EnableVertexAttribArray(attribute.location);
+            }
+        }
+    }
+}
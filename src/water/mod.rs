@@ -0,0 +1,282 @@
+pub mod normal_map;
+pub mod water_settings;
+
+use std::{
+    collections::HashMap,
+    ptr,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    aabb::Aabb,
+    camera::frustum::Frustum,
+    chunk::chunk::Chunk,
+    light::directional_light::DirectionalLight,
+    mesh::{gpu_mesh::GpuMesh, Mesh},
+    render_target::RenderTarget,
+    shader::Shader,
+    utils,
+    vertex_layout::{AttributeType, VertexAttribute, VertexLayout},
+    CHUNK_PIXEL_SIZE,
+};
+
+use self::water_settings::WaterSettings;
+
+// Grid units between water vertices, the surface is flat so it needs far fewer than the terrain
+const WATER_GRID_STEP: usize = 4;
+
+const NORMAL_MAP_SIZE: usize = 256;
+const NORMAL_MAP_SEED: u64 = 7;
+
+const REFLECTION_TEXTURE_UNIT: u32 = 2;
+const NORMAL_MAP_TEXTURE_UNIT: u32 = 3;
+
+struct WaterChunk {
+    // The terrain chunk the mesh was built from, a regenerated chunk means new heights
+    source: Weak<Chunk>,
+    sea_level: f32,
+    world_position: glm::Vec3,
+    // None when the whole chunk lies above the sea level
+    mesh: Option<GpuMesh>,
+}
+
+// Water surfaces over the parts of the visible chunks that lie below the sea level
+pub struct Water {
+    pub shader: Shader,
+    normal_map_texture_id: u32,
+    chunks: HashMap<(i32, i32), WaterChunk>,
+
+    // The terrain mirrored in the water plane, rendered before the water is drawn
+    pub reflection: RenderTarget,
+}
+
+impl Water {
+    pub fn new(shader: Shader, reflection_width: i32, reflection_height: i32) -> Self {
+        let texels = normal_map::generate(NORMAL_MAP_SIZE, NORMAL_MAP_SEED);
+
+        let mut normal_map_texture_id: u32 = 0;
+        unsafe {
+            gl::GenTextures(1, &mut normal_map_texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, normal_map_texture_id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB8 as i32,
+                NORMAL_MAP_SIZE as i32,
+                NORMAL_MAP_SIZE as i32,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                utils::pointer_to_array(&texels),
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR_MIPMAP_LINEAR as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Water {
+            shader,
+            normal_map_texture_id,
+            chunks: HashMap::new(),
+            reflection: RenderTarget::new(reflection_width, reflection_height),
+        }
+    }
+
+    // Builds water meshes for new or changed chunks and frees the ones that went out of view.
+    // Chunks hidden entirely by fog get no water, as their terrain isn't drawn either.
+    pub fn update(
+        &mut self,
+        visible_chunks: &[Rc<Chunk>],
+        sea_level: f32,
+        camera_position: &glm::Vec3,
+        max_distance: f32,
+    ) {
+        let visible_chunks: Vec<&Rc<Chunk>> = visible_chunks
+            .iter()
+            .filter(|chunk| {
+                let mut bounds = chunk.bounds();
+                bounds.grow(&glm::vec3(bounds.min.x, sea_level, bounds.min.z));
+                bounds.distance_to(camera_position) <= max_distance
+            })
+            .collect();
+
+        for chunk in &visible_chunks {
+            if let Some(water_chunk) = self.chunks.get(&chunk.position) {
+                if ptr::eq(water_chunk.source.as_ptr(), Rc::as_ptr(chunk))
+                    && water_chunk.sea_level == sea_level
+                {
+                    continue;
+                }
+            }
+
+            let mesh = Water::create_mesh(chunk, sea_level).map(|mesh| GpuMesh::new(&mesh));
+            let water_chunk = WaterChunk {
+                source: Rc::downgrade(chunk),
+                sea_level,
+                world_position: chunk.world_position() + glm::vec3(0.0, sea_level, 0.0),
+                mesh,
+            };
+            self.chunks.insert(chunk.position, water_chunk);
+        }
+
+        self.chunks.retain(|position, _| {
+            visible_chunks
+                .iter()
+                .any(|chunk| chunk.position == *position)
+        });
+    }
+
+    // A flat grid at height zero covering every cell with terrain below the sea level. Each
+    // vertex carries the water depth above the terrain there.
+    fn create_mesh(chunk: &Chunk, sea_level: f32) -> Option<Mesh> {
+        let height_field = &chunk.height_field;
+        let cells_per_line = CHUNK_PIXEL_SIZE as usize / WATER_GRID_STEP;
+        let vertices_per_line = cells_per_line + 1;
+        let half_size = CHUNK_PIXEL_SIZE as f32 / 2.0;
+
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::new("position", 0, 3, AttributeType::Float, false),
+            VertexAttribute::new("depth", 1, 1, AttributeType::Float, false),
+        ]);
+
+        let mut vertex_data: Vec<u8> = Vec::new();
+        let mut vertex_count = 0;
+        let mut indices: Vec<u32> = Vec::new();
+        let mut bounds = Aabb::empty();
+
+        // Grid vertices are only emitted once a wet cell uses them
+        let mut vertex_indices = vec![u32::MAX; vertices_per_line * vertices_per_line];
+        let mut vertex_index = |grid_x: usize, grid_z: usize, vertex_data: &mut Vec<u8>| {
+            let slot = &mut vertex_indices[grid_z * vertices_per_line + grid_x];
+            if *slot == u32::MAX {
+                let x = grid_x * WATER_GRID_STEP;
+                let z = grid_z * WATER_GRID_STEP;
+                let position = glm::vec3(-half_size + x as f32, 0.0, half_size - z as f32);
+                let depth = sea_level - height_field.height(x as i32, z as i32);
+
+                layout.push_vertex(vertex_data, &[position.as_slice(), &[depth]]);
+                bounds.grow(&position);
+                *slot = vertex_count;
+                vertex_count += 1;
+            }
+            *slot
+        };
+
+        for cell_z in 0..cells_per_line {
+            for cell_x in 0..cells_per_line {
+                let mut lowest = f32::MAX;
+                for z in cell_z * WATER_GRID_STEP..=(cell_z + 1) * WATER_GRID_STEP {
+                    for x in cell_x * WATER_GRID_STEP..=(cell_x + 1) * WATER_GRID_STEP {
+                        lowest = lowest.min(height_field.height(x as i32, z as i32));
+                    }
+                }
+                if lowest >= sea_level {
+                    continue;
+                }
+
+                let near_left = vertex_index(cell_x, cell_z, &mut vertex_data);
+                let near_right = vertex_index(cell_x + 1, cell_z, &mut vertex_data);
+                let far_left = vertex_index(cell_x, cell_z + 1, &mut vertex_data);
+                let far_right = vertex_index(cell_x + 1, cell_z + 1, &mut vertex_data);
+
+                // Counter clockwise seen from above, split along the same diagonal as the terrain
+                indices.extend([near_left, near_right, far_right]);
+                indices.extend([near_left, far_right, far_left]);
+            }
+        }
+
+        if indices.is_empty() {
+            return None;
+        }
+        Some(Mesh::new(
+            vertex_data,
+            layout,
+            vertex_count as usize,
+            indices,
+            bounds,
+        ))
+    }
+
+    pub fn resize_reflection(&mut self, window_width: i32, window_height: i32) {
+        self.reflection.resize(window_width / 2, window_height / 2);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        settings: &WaterSettings,
+        view_projection_matrix: &glm::Mat4,
+        frustum: &Frustum,
+        camera_position: &glm::Vec3,
+        sun: &DirectionalLight,
+        time: f32,
+        screen_size: (f32, f32),
+    ) {
+        self.shader.set_vec3("camera_position", camera_position);
+        self.shader.set_struct("sun", sun);
+
+        self.shader.set_f32("time", time * settings.wave_speed);
+        self.shader
+            .set_vec3("shallow_color", &settings.shallow_color.into());
+        self.shader
+            .set_vec3("deep_color", &settings.deep_color.into());
+        self.shader.set_f32("max_depth", settings.max_depth);
+        self.shader.set_f32("wave_scale", settings.wave_scale);
+        self.shader.set_f32("wave_strength", settings.wave_strength);
+        self.shader
+            .set_vec2("screen_size", &glm::vec2(screen_size.0, screen_size.1));
+        self.shader
+            .set_i32("reflections", settings.reflections as i32);
+
+        unsafe {
+            self.shader.activate();
+
+            gl::ActiveTexture(gl::TEXTURE0 + REFLECTION_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.reflection.color_texture_id);
+            gl::ActiveTexture(gl::TEXTURE0 + NORMAL_MAP_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.normal_map_texture_id);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            // The surface is see through, it must not hide terrain drawn after it, and it
+            // should still show when the camera dives below it
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+
+            for water_chunk in self.chunks.values() {
+                let mesh = match &water_chunk.mesh {
+                    Some(mesh) => mesh,
+                    None => continue,
+                };
+                if !frustum.intersects_aabb(&mesh.bounds.translated(&water_chunk.world_position)) {
+                    continue;
+                }
+
+                let model_matrix = glm::translation(&water_chunk.world_position);
+                let transformation_matrix = view_projection_matrix * model_matrix;
+
+                mesh.bind();
+                self.shader
+                    .set_mat4("transform_matrix", &transformation_matrix);
+                self.shader.set_mat4("model_matrix", &model_matrix);
+                mesh.draw_elements();
+            }
+
+            gl::Enable(gl::CULL_FACE);
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+// Mirrors world space in the horizontal plane at the given height
+pub fn reflection_matrix(sea_level: f32) -> glm::Mat4 {
+    glm::translation(&glm::vec3(0.0, sea_level, 0.0))
+        * glm::scaling(&glm::vec3(1.0, -1.0, 1.0))
+        * glm::translation(&glm::vec3(0.0, -sea_level, 0.0))
+}
@@ -0,0 +1,48 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const WAVE_COUNT: usize = 12;
+
+// A tileable normal map of overlapping sine waves, as RGB bytes with the normal's z in blue.
+// Every wave completes a whole number of periods across the map so the edges line up.
+pub fn generate(size: usize, seed: u64) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Frequency in periods per tile along x and y, amplitude and phase of every wave
+    let waves: Vec<([f32; 2], f32, f32)> = (0..WAVE_COUNT)
+        .map(|wave| {
+            let frequency = 1 + wave as i32 / 2;
+            let direction = [
+                rng.gen_range(-frequency..=frequency) as f32,
+                rng.gen_range(-frequency..=frequency) as f32,
+            ];
+            let amplitude = 1.0 / (1.0 + wave as f32);
+            let phase = rng.gen_range(0.0..std::f32::consts::TAU);
+            (direction, amplitude, phase)
+        })
+        .collect();
+
+    let mut texels = Vec::with_capacity(size * size * 3);
+    for y in 0..size {
+        for x in 0..size {
+            let u = x as f32 / size as f32;
+            let v = y as f32 / size as f32;
+
+            // Height derivatives along u and v, summed over the waves
+            let mut slope = [0.0, 0.0];
+            for (direction, amplitude, phase) in &waves {
+                let angle = std::f32::consts::TAU * (direction[0] * u + direction[1] * v) + phase;
+                let derivative = amplitude * angle.cos() * std::f32::consts::TAU;
+                slope[0] += derivative * direction[0];
+                slope[1] += derivative * direction[1];
+            }
+
+            let normal = glm::normalize(&glm::vec3(-slope[0] * 0.02, -slope[1] * 0.02, 1.0));
+            texels.extend(
+                normal
+                    .iter()
+                    .map(|n| ((n * 0.5 + 0.5) * 255.0).round() as u8),
+            );
+        }
+    }
+    texels
+}
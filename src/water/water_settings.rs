@@ -0,0 +1,46 @@
+use imgui::{CollapsingHeader, Ui};
+
+#[derive(Clone, PartialEq)]
+pub struct WaterSettings {
+    pub name: String,
+    // World height of the water surface
+    pub sea_level: f32,
+    pub wave_speed: f32,
+    // World units covered by one tile of the wave normal map
+    pub wave_scale: f32,
+    pub wave_strength: f32,
+    pub shallow_color: [f32; 3],
+    pub deep_color: [f32; 3],
+    // Depth at which the water reaches its deep color and full opacity
+    pub max_depth: f32,
+    pub reflections: bool,
+}
+
+impl WaterSettings {
+    pub fn new(name: String, sea_level: f32) -> Self {
+        Self {
+            name,
+            sea_level,
+            wave_speed: 1.0,
+            wave_scale: 40.0,
+            wave_strength: 0.4,
+            shallow_color: [0.1, 0.45, 0.5],
+            deep_color: [0.02, 0.08, 0.2],
+            max_depth: 4.0,
+            reflections: true,
+        }
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        if CollapsingHeader::new(&self.name).build(ui) {
+            ui.slider("Sea level", -10.0, 30.0, &mut self.sea_level);
+            ui.slider("Wave speed", 0.0, 5.0, &mut self.wave_speed);
+            ui.slider("Wave scale", 5.0, 200.0, &mut self.wave_scale);
+            ui.slider("Wave strength", 0.0, 1.0, &mut self.wave_strength);
+            ui.color_edit3("Shallow color", &mut self.shallow_color);
+            ui.color_edit3("Deep color", &mut self.deep_color);
+            ui.slider("Max depth", 0.1, 20.0, &mut self.max_depth);
+            ui.checkbox("Reflections", &mut self.reflections);
+        }
+    }
+}